@@ -69,3 +69,133 @@ pub fn generate_playback_policy(client: &livepeer_rs::Livepeer) -> Option<serde_
 
     return playback_policy;
 }
+
+// Build a playback policy from its command line form: public, jwt or webhook:<webhook id>
+pub fn parse_playback_policy(policy: &str) -> Result<Option<serde_json::Value>, String> {
+    match policy.split_once(':') {
+        Some(("webhook", webhook_id)) if !webhook_id.is_empty() => Ok(Some(serde_json::json!({
+            "type": "webhook",
+            "webhookId": webhook_id,
+            "webhookContext": {}
+        }))),
+        None if policy == "public" => Ok(None),
+        None if policy == "jwt" => Ok(Some(serde_json::json!({
+            "type": "jwt",
+        }))),
+        _ => Err(format!(
+            "Invalid playback policy {}, expected public, jwt or webhook:<id>",
+            policy
+        )),
+    }
+}
+
+// A missing policy means the resource is public
+fn effective_policy(policy: &serde_json::Value) -> serde_json::Value {
+    if policy.is_null() {
+        serde_json::json!({ "type": "public" })
+    } else {
+        policy.clone()
+    }
+}
+
+// Leaf values of a policy keyed by their path, e.g. webhookContext.foo
+fn flatten(prefix: &str, value: &serde_json::Value, out: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let key = if prefix.is_empty() {
+                    k.clone()
+                } else {
+                    format!("{}.{}", prefix, k)
+                };
+                flatten(&key, v, out);
+            }
+        }
+        _ => out.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+// Lines removed (-) and added (+) going from one policy to the other, unchanged lines start with a space
+pub fn policy_diff(before: &serde_json::Value, after: &serde_json::Value) -> Vec<String> {
+    let (mut old, mut new) = (vec![], vec![]);
+    flatten("", &effective_policy(before), &mut old);
+    flatten("", &effective_policy(after), &mut new);
+
+    let mut lines = vec![];
+    for (k, v) in &old {
+        match new.iter().find(|(nk, _)| nk == k) {
+            Some((_, nv)) if nv == v => lines.push(format!("  {}: {}", k, v)),
+            _ => lines.push(format!("- {}: {}", k, v)),
+        }
+    }
+    for (k, v) in &new {
+        if !old.contains(&(k.clone(), v.clone())) {
+            lines.push(format!("+ {}: {}", k, v));
+        }
+    }
+    lines
+}
+
+pub fn print_policy_diff(before: &serde_json::Value, after: &serde_json::Value) {
+    use colored::*;
+    for line in policy_diff(before, after) {
+        match line.chars().next() {
+            Some('-') => eprintln!("{}", line.red()),
+            Some('+') => eprintln!("{}", line.green()),
+            _ => eprintln!("{}", line),
+        }
+    }
+}
+
+// Replace the playback policy of a stream or asset ("stream" or "asset") and return it refreshed
+pub fn set_playback_policy(
+    kind: &str,
+    id: &str,
+    policy: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let policy = effective_policy(&policy.unwrap_or(serde_json::Value::Null));
+    crate::api::patch(
+        &format!("/api/{}/{}", kind, id),
+        serde_json::json!({ "playbackPolicy": policy }),
+    )
+    .map_err(|e| format!("Error changing the policy: {}", e))?;
+    crate::api::get(&format!("/api/{}/{}", kind, id))
+}
+
+// Pick a new policy for a stream or asset, show what changes and apply it once confirmed
+pub fn change_policy(
+    client: &livepeer_rs::Livepeer,
+    kind: &str,
+    resource: &serde_json::Value,
+) -> Option<serde_json::Value> {
+    let before = resource["playbackPolicy"].clone();
+    println!(
+        "Current policy: {}",
+        serde_json::to_string_pretty(&effective_policy(&before)).unwrap()
+    );
+    let after = generate_playback_policy(client).unwrap_or(serde_json::Value::Null);
+    if effective_policy(&before) == effective_policy(&after) {
+        info!("The policy is unchanged");
+        return None;
+    }
+    print_policy_diff(&before, &after);
+
+    let confirm = dialoguer::Confirm::new()
+        .with_prompt(format!("Apply the new policy to {} {}?", kind, resource["id"]))
+        .default(false)
+        .interact()
+        .unwrap();
+    if !confirm {
+        return None;
+    }
+    match set_playback_policy(kind, resource["id"].as_str().unwrap_or(""), Some(after)) {
+        Ok(updated) => {
+            info!("Policy updated");
+            Some(updated)
+        }
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// Calls to the Studio endpoints livepeer_rs doesn't wrap, made with the api key and env of the
+// client currently in use
+
+use crate::assets::upload::retry::Error;
+
+// Overrides the api host of the env, e.g. for a local Studio
+const HOST_VAR: &str = "LIVEPEER_STUDIO_API_HOST";
+
+// Envs the CLI can talk to, in the order of the env menu
+pub const ENVS: [&str; 4] = ["prod", "stg", "dev", "box"];
+
+// Hosts of an env, every url of the env is derived from them
+struct Hosts {
+    api: &'static str,
+    ingest: &'static str,
+    srt_port: u16,
+    cdn: &'static str,
+    // Ingest has a host per region, e.g. fra-rtmp.livepeer.com
+    regional: bool,
+}
+
+// The livepeer_rs env and the hosts of an env, so the livepeer_rs client and the calls made here
+// always target the same Studio. Dev and box are a Studio run locally (livepeer/box on port
+// 8888), set LIVEPEER_STUDIO_API_HOST when the local api listens elsewhere.
+fn env(name: &str) -> (livepeer_rs::LivepeerEnv, Hosts) {
+    let local = Hosts {
+        api: "http://localhost:8888",
+        ingest: "localhost",
+        srt_port: 8889,
+        cdn: "http://localhost:8888",
+        regional: false,
+    };
+    match name {
+        "prod" => (
+            livepeer_rs::LivepeerEnv::Prod,
+            Hosts {
+                api: "https://livepeer.studio",
+                ingest: "rtmp.livepeer.com",
+                srt_port: 2935,
+                cdn: "https://livepeercdn.studio",
+                regional: true,
+            },
+        ),
+        "stg" => (
+            livepeer_rs::LivepeerEnv::Stg,
+            Hosts {
+                api: "https://livepeer.monster",
+                ingest: "rtmp.livepeer.monster",
+                srt_port: 2935,
+                cdn: "https://livepeercdn.monster",
+                regional: true,
+            },
+        ),
+        "dev" => (livepeer_rs::LivepeerEnv::Dev, local),
+        "box" => (livepeer_rs::LivepeerEnv::Box, local),
+        // The env comes from the env menu or --env, both limited to ENVS
+        other => panic!("Unknown env {}, expected one of {:?}", other, ENVS),
+    }
+}
+
+pub fn lvpr_env(name: &str) -> livepeer_rs::LivepeerEnv {
+    env(name).0
+}
+
+fn hosts() -> Hosts {
+    env(&crate::auth::current_env()).1
+}
+
+// Where streams are pushed to and played from on an env
+pub struct Endpoints {
+    pub api: String,
+    pub rtmp: String,
+    pub srt: String,
+    pub webrtc: String,
+    pub hls: String,
+    pub player: String,
+}
+
+pub fn endpoints() -> Endpoints {
+    let hosts = hosts();
+    let mut endpoints = Endpoints {
+        api: hosts.api.to_string(),
+        rtmp: format!("rtmp://{}/live", hosts.ingest),
+        srt: format!("srt://{}:{}", hosts.ingest, hosts.srt_port),
+        webrtc: format!("{}/webrtc", hosts.api),
+        hls: format!("{}/hls", hosts.cdn),
+        player: String::from("https://lvpr.tv"),
+    };
+    if let Ok(host) = std::env::var(HOST_VAR) {
+        endpoints.api = host.trim_end_matches('/').to_string();
+    }
+    endpoints
+}
+
+// RTMP ingest of one region, envs without regional ingest use their only one
+pub fn regional_rtmp(region: &str) -> String {
+    let hosts = hosts();
+    if hosts.regional {
+        format!("rtmp://{}-{}/live", region, hosts.ingest)
+    } else {
+        endpoints().rtmp
+    }
+}
+
+fn request(
+    method: surf::http::Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    send(method, path, body).map_err(String::from)
+}
+
+// Like `request`, keeping the status of a failed response so callers can tell which failures are
+// worth retrying. Network failures have no status and are retriable.
+pub fn send(
+    method: surf::http::Method,
+    path: &str,
+    body: Option<serde_json::Value>,
+) -> Result<serde_json::Value, Error> {
+    let api_key =
+        crate::auth::current_api_key().ok_or(Error::fatal(String::from("No API key in use")))?;
+    let url = format!("{}{}", endpoints().api, path);
+    debug!("{} {}", method, url);
+
+    let (status, text) = async_std::task::block_on(async {
+        let mut request = surf::RequestBuilder::new(method, surf::Url::parse(&url)?)
+            .header("Authorization", format!("Bearer {}", api_key));
+        if let Some(b) = &body {
+            request = request.body_json(b)?;
+        }
+        let mut response = request.await?;
+        let text = response.body_string().await?;
+        Ok::<_, surf::Error>((response.status(), text))
+    })
+    .map_err(|e| Error::from(format!("{} {}: {}", method, path, e)))?;
+
+    let value = if text.trim().is_empty() {
+        serde_json::Value::Null
+    } else {
+        serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text))
+    };
+    if !status.is_success() {
+        // Studio errors look like {"errors": ["..."]}
+        let errors = value["errors"]
+            .as_array()
+            .map(|e| {
+                e.iter()
+                    .map(|m| m.as_str().map_or(m.to_string(), String::from))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            })
+            .unwrap_or(value.to_string());
+        return Err(Error::http(
+            status as u16,
+            format!("{} {}: {} {}", method, path, status as u16, errors),
+        ));
+    }
+    Ok(value)
+}
+
+pub fn get(path: &str) -> Result<serde_json::Value, String> {
+    request(surf::http::Method::Get, path, None)
+}
+
+pub fn post(path: &str, body: serde_json::Value) -> Result<serde_json::Value, String> {
+    request(surf::http::Method::Post, path, Some(body))
+}
+
+pub fn patch(path: &str, body: serde_json::Value) -> Result<serde_json::Value, String> {
+    request(surf::http::Method::Patch, path, Some(body))
+}
+
+pub fn put(path: &str, body: serde_json::Value) -> Result<serde_json::Value, String> {
+    request(surf::http::Method::Put, path, Some(body))
+}
+
+pub fn delete(path: &str) -> Result<serde_json::Value, String> {
+    request(surf::http::Method::Delete, path, None)
+}
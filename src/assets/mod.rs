@@ -1,4 +1,5 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
+use crate::output::truncate_and_pad;
 use colored::*;
 use livepeer_rs::{
     playback::Playback,
@@ -7,15 +8,6 @@ use livepeer_rs::{
 
 pub mod upload;
 
-fn truncate_and_pad(s: &str, max_width: usize, min_width: usize) -> String {
-    let truncated = if s.len() > max_width {
-        s.chars().take(max_width).collect::<String>()
-    } else {
-        s.to_string()
-    };
-    format!("{:<width$}   ", truncated, width = min_width)
-}
-
 pub fn assets(client: &livepeer_rs::Livepeer) -> bool {
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
         .items(&[
@@ -24,6 +16,7 @@ pub fn assets(client: &livepeer_rs::Livepeer) -> bool {
             "Get Assets by CID",
             "Get Asset By ID or PlaybackID",
             "Upload Asset",
+            "Upload Queue",
             "Test (Upload -> Task -> Playback -> Export to IPFS)",
             "< Back",
         ])
@@ -95,10 +88,14 @@ fn handle_selection(index: usize, client: &livepeer_rs::Livepeer) {
             assets(client);
         }
         5 => {
-            test_asset_flow(client);
+            upload::queue::queue(client);
             assets(client);
         }
         6 => {
+            test_asset_flow(client);
+            assets(client);
+        }
+        7 => {
             crate::list_options(&client);
             std::process::exit(0);
         }
@@ -113,7 +110,7 @@ fn handle_selection(index: usize, client: &livepeer_rs::Livepeer) {
     handle_asset_list_selection(asset_list, client, e);
 }
 
-fn get_asset_by_id_or_playback_id(client: &livepeer_rs::Livepeer, asset_id: String) -> Option<serde_json::Value> {
+pub fn get_asset_by_id_or_playback_id(client: &livepeer_rs::Livepeer, asset_id: String) -> Option<serde_json::Value> {
     let single_asset = client.asset.get_asset_by_id(asset_id.clone());
     if let Ok(a) = single_asset {
         Some(a)
@@ -243,6 +240,8 @@ pub fn inspect_asset(asset: Option<serde_json::Value>, client: &livepeer_rs::Liv
             "Open in lvpr.tv (Webrtc)",
             "Open in lvpr.tv (HLS)",
             "Export to IPFS",
+            "Change policy",
+            "Create clip",
             "< Back",
             "< Home",
         ])
@@ -332,9 +331,17 @@ fn handle_inspect_selection(index: usize, a: serde_json::Value, task: Result<ser
             }
         }
         7 => {
-            assets(client);
+            let updated = crate::accesscontrol::change_policy(client, "asset", &a);
+            inspect_asset(Some(updated.unwrap_or(a)), client);
         }
         8 => {
+            crate::clips::clip_asset(client, &a);
+            inspect_asset(Some(a), client);
+        }
+        9 => {
+            assets(client);
+        }
+        10 => {
             crate::list_options(&client);
             std::process::exit(0);
         }
@@ -349,7 +356,11 @@ pub fn test_asset_flow(client: &livepeer_rs::Livepeer) -> bool {
         .to_str()
         .unwrap()
         .to_string();
-    let result = upload::do_upload(client, &current_folder_string, false);
+    let result = upload::do_upload(
+        client,
+        &current_folder_string,
+        &upload::UploadOptions::default(),
+    );
     if result.is_none() {
         println!("❌ - Error uploading asset");
         return false;
@@ -361,15 +372,19 @@ pub fn test_asset_flow(client: &livepeer_rs::Livepeer) -> bool {
     let task_id = res.task_id;
     let playback_id = res.playback_id;
 
-    let task_result = super::tasks::track_task_status(
-        serde_json::from_str(&format!("{}{}{}", r#"{"id":""#, task_id, r#""}"#)).unwrap(),
-        client,
-    );
-    if !task_result {
-        println!("❌ - Task failed");
-        return false;
+    if res.duplicate {
+        println!("✅ - Content already uploaded, using the existing asset");
+    } else {
+        let task_result = super::tasks::track_task_status(
+            serde_json::from_str(&format!("{}{}{}", r#"{"id":""#, task_id, r#""}"#)).unwrap(),
+            client,
+        );
+        if !task_result {
+            println!("❌ - Task failed");
+            return false;
+        }
+        println!("✅ - Task completed");
     }
-    println!("✅ - Task completed");
     let playback_info = client.playback.get_playback_info(&playback_id);
     if playback_info.is_err() {
         println!("❌ - Error getting playback info");
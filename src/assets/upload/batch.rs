@@ -0,0 +1,204 @@
+use super::{UploadAssetResult, UploadOptions};
+
+pub struct BatchUploadResult {
+    pub path: String,
+    pub result: Option<UploadAssetResult>,
+}
+
+// Expand a folder (optionally recursing into subfolders) or a glob into the video files it contains
+pub fn collect_files(folder_or_glob: &String, recursive: bool) -> Result<Vec<String>, String> {
+    let path = std::path::Path::new(folder_or_glob);
+    let mut files = vec![];
+
+    if path.is_dir() {
+        let mut folders = vec![path.to_path_buf()];
+        while let Some(folder) = folders.pop() {
+            for entry in std::fs::read_dir(&folder).map_err(|e| e.to_string())? {
+                let entry_path = entry.map_err(|e| e.to_string())?.path();
+                let hidden = entry_path
+                    .file_name()
+                    .map_or(false, |f| f.to_string_lossy().starts_with("."));
+                if entry_path.is_dir() && recursive && !hidden {
+                    folders.push(entry_path);
+                } else if super::is_video_file(&entry_path) {
+                    files.push(entry_path.to_string_lossy().to_string());
+                }
+            }
+        }
+    } else {
+        let options = glob::MatchOptions {
+            require_literal_leading_dot: true,
+            ..Default::default()
+        };
+        for entry in glob::glob_with(folder_or_glob, options).map_err(|e| e.to_string())? {
+            let entry_path = entry.map_err(|e| e.to_string())?;
+            if super::is_video_file(&entry_path) {
+                files.push(entry_path.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// Upload every file using the file name as asset name, at most `concurrency` at a time
+pub fn upload_files(
+    client: &livepeer_rs::Livepeer,
+    files: Vec<String>,
+    playback_policy: Option<serde_json::Value>,
+    concurrency: usize,
+    options: &UploadOptions,
+) -> Vec<BatchUploadResult> {
+    let options = options.unattended();
+    // Queue every file first so the ones not reached survive a crash, reversed so popping
+    // from the end hands files out in order
+    let queue = std::sync::Mutex::new(
+        files
+            .into_iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let asset_name = std::path::Path::new(&path)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                let id = super::queue::enqueue(
+                    &path,
+                    asset_name,
+                    playback_policy.clone(),
+                    options.clone(),
+                );
+                if let Err(e) = &id {
+                    error!("Not uploading {}: {}", path, e);
+                }
+                (index, path, id.ok())
+            })
+            .rev()
+            .collect::<Vec<_>>(),
+    );
+    let results = std::sync::Mutex::new(vec![]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            let worker_client = client.clone();
+            let queue = &queue;
+            let results = &results;
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().pop();
+                let (index, path, id) = match next {
+                    Some(n) => n,
+                    None => break,
+                };
+                let result = id.and_then(|id| super::queue::run_entry(&worker_client, &id));
+                results
+                    .lock()
+                    .unwrap()
+                    .push((index, BatchUploadResult { path, result }));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+pub fn summary(results: &[BatchUploadResult]) -> serde_json::Value {
+    serde_json::Value::Array(
+        results
+            .iter()
+            .map(|r| match &r.result {
+                Some(u) => serde_json::json!({
+                    "file": r.path,
+                    "status": if u.duplicate { "duplicate" } else { "uploaded" },
+                    "assetId": u.asset_id,
+                    "taskId": u.task_id,
+                    "playbackId": u.playback_id,
+                }),
+                None => serde_json::json!({
+                    "file": r.path,
+                    "status": "failed",
+                    "assetId": "",
+                    "taskId": "",
+                    "playbackId": "",
+                }),
+            })
+            .collect(),
+    )
+}
+
+pub fn bulk_upload(client: &livepeer_rs::Livepeer) {
+    let current_folder_string = std::env::current_dir()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let folder_or_glob = dialoguer::Input::<String>::new()
+        .with_prompt("Enter a folder or a glob (e.g. ./episodes/*.mp4)")
+        .default(current_folder_string)
+        .interact()
+        .unwrap();
+    let recursive = dialoguer::Confirm::new()
+        .with_prompt("Include subfolders?")
+        .default(false)
+        .interact()
+        .unwrap();
+
+    let files = match collect_files(&folder_or_glob, recursive) {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Error listing files: {}", e);
+            return;
+        }
+    };
+    if files.is_empty() {
+        warn!("No video files found in {}", folder_or_glob);
+        return;
+    }
+    println!("Found {} files to upload", files.len());
+    for file in &files {
+        println!("  {}", file);
+    }
+
+    let concurrency = dialoguer::Input::<usize>::new()
+        .with_prompt("How many files to upload at the same time?")
+        .default(2)
+        .interact()
+        .unwrap();
+    // Asked once here, the uploads run side by side and can't prompt
+    let duplicates = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Files already uploaded to this env")
+        .items(&["Skip them", "Upload them again"])
+        .default(0)
+        .interact()
+        .unwrap();
+    let problems = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Files with problems found by ffprobe")
+        .items(&["Upload them with a warning", "Skip them"])
+        .default(0)
+        .interact()
+        .unwrap();
+    let options = UploadOptions {
+        on_duplicate: match duplicates {
+            0 => super::dedup::DuplicateAction::Skip,
+            _ => super::dedup::DuplicateAction::Warn,
+        },
+        preflight: match problems {
+            0 => super::probe::PreflightAction::Warn,
+            _ => super::probe::PreflightAction::Reject,
+        },
+        ..Default::default()
+    };
+    let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
+
+    let results = upload_files(client, files, playback_policy, concurrency, &options);
+    println!(
+        "{}",
+        crate::output::render(
+            &summary(&results),
+            crate::output::Resource::Upload,
+            crate::output::OutputFormat::Table
+        )
+    );
+}
@@ -0,0 +1,237 @@
+use std::io::BufRead;
+
+use super::probe::MediaInfo;
+
+const HEIGHTS: &'static [u64] = &[2160, 1440, 1080, 720, 480, 360];
+// Lines of ffmpeg output shown when a conversion fails
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum ConvertMode {
+    /// Copy the streams into an MP4 with the index at the start (fast, fixes the container only)
+    Remux,
+    /// Re-encode to constant frame rate H.264/AAC in an MP4
+    Transcode,
+}
+
+// A processed copy of a file in the temp folder
+pub struct ConvertedFile {
+    pub path: String,
+}
+
+impl ConvertedFile {
+    pub fn remove(&self) {
+        match std::fs::remove_file(&self.path) {
+            Ok(_) => debug!("Removed {}", self.path),
+            Err(e) => warn!("Unable to remove {}: {}", self.path, e),
+        }
+    }
+}
+
+fn temp_path(path: &String) -> String {
+    let stem = std::path::Path::new(path)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    std::env::temp_dir()
+        .join(format!("studio-{}-{}.mp4", nanoid::nanoid!(6), stem))
+        .to_string_lossy()
+        .to_string()
+}
+
+fn ffmpeg_args(
+    path: &String,
+    output: &String,
+    mode: ConvertMode,
+    info: &MediaInfo,
+    max_height: Option<u64>,
+) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-y", "-v", "error", "-progress", "pipe:1", "-nostats", "-i"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    args.push(path.clone());
+
+    match mode {
+        ConvertMode::Remux => {
+            args.extend(
+                ["-map", "0:v:0?", "-map", "0:a:0?", "-c", "copy"]
+                    .into_iter()
+                    .map(String::from),
+            );
+        }
+        ConvertMode::Transcode => {
+            // Add a silent track to files without audio
+            if info.audio_codec.is_none() {
+                args.extend(
+                    [
+                        "-f",
+                        "lavfi",
+                        "-i",
+                        "anullsrc=channel_layout=stereo:sample_rate=48000",
+                        "-shortest",
+                    ]
+                    .into_iter()
+                    .map(String::from),
+                );
+            }
+            let audio_input = if info.audio_codec.is_none() {
+                "1:a:0"
+            } else {
+                "0:a:0"
+            };
+            args.extend(
+                ["-map", "0:v:0", "-map", audio_input]
+                    .into_iter()
+                    .map(String::from),
+            );
+
+            let mut filters = vec![];
+            if info.interlaced {
+                filters.push(String::from("yadif"));
+            }
+            if let Some(h) = max_height {
+                filters.push(format!("scale=-2:'min({},ih)'", h));
+            }
+            // Odd sizes can't be encoded as yuv420p
+            filters.push(String::from("scale=trunc(iw/2)*2:trunc(ih/2)*2"));
+            args.push(String::from("-vf"));
+            args.push(filters.join(","));
+
+            args.extend(
+                [
+                    "-c:v", "libx264", "-preset", "veryfast", "-crf", "20", "-pix_fmt", "yuv420p",
+                    "-c:a", "aac", "-b:a", "128k", "-ac", "2",
+                ]
+                .into_iter()
+                .map(String::from),
+            );
+            // Forcing the output rate makes variable frame rate inputs constant
+            if let Some(fps) = info.fps {
+                args.push(String::from("-r"));
+                args.push(format!("{:.3}", fps));
+            }
+        }
+    }
+
+    args.extend(["-movflags", "+faststart"].into_iter().map(String::from));
+    args.push(output.clone());
+    args
+}
+
+// Remux or transcode a file into a temp MP4, showing ffmpeg progress
+pub fn convert(
+    path: &String,
+    mode: ConvertMode,
+    info: &MediaInfo,
+    max_height: Option<u64>,
+) -> Result<ConvertedFile, String> {
+    let ffmpeg = crate::live::get_ffmpeg_path()?;
+    let output = temp_path(path);
+    let converted = ConvertedFile {
+        path: output.clone(),
+    };
+
+    info!("{:?} {} into {}", mode, path, output);
+    let mut child = std::process::Command::new(ffmpeg)
+        .args(ffmpeg_args(path, &output, mode, info, max_height))
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // Drained alongside the progress so a chatty ffmpeg never blocks on a full stderr pipe, only
+    // the last lines are kept for the error message
+    let stderr = child.stderr.take().unwrap();
+    let stderr_tail = std::thread::spawn(move || {
+        let mut tail = std::collections::VecDeque::new();
+        for line in std::io::BufReader::new(stderr)
+            .lines()
+            .map_while(Result::ok)
+        {
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        Vec::from(tail).join("\n")
+    });
+
+    let duration_ms = info.duration.map_or(0, |d| (d * 1000.0) as u64);
+    let pb = indicatif::ProgressBar::new(duration_ms);
+    pb.set_style(crate::tasks::progress_style("{percent}% {msg}"));
+    pb.set_message(format!("{:?}", mode));
+
+    // -progress writes key=value blocks, out_time_us is the position reached in the output
+    for line in std::io::BufReader::new(child.stdout.take().unwrap()).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if let Some(us) = line.strip_prefix("out_time_us=") {
+            if let Ok(us) = us.parse::<u64>() {
+                pb.set_position(us / 1000);
+            }
+        }
+    }
+    pb.finish_and_clear();
+
+    let status = child.wait().map_err(|e| e.to_string())?;
+    let stderr_tail = stderr_tail.join().unwrap_or_default();
+    if !status.success() {
+        converted.remove();
+        return Err(format!("ffmpeg failed: {}", stderr_tail.trim()));
+    }
+    Ok(converted)
+}
+
+// Offer to remux or transcode a file that failed the preflight, returns the file to upload
+// and the temp copy to remove afterwards, or None when the upload is cancelled
+pub fn prepare(path: &String, info: &MediaInfo) -> Option<(String, Option<ConvertedFile>)> {
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("This file may not process correctly")
+        .items(&[
+            "Upload as is",
+            "Remux to MP4 (faststart)",
+            "Transcode to H.264/AAC MP4",
+            "Cancel",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    let (mode, max_height) = match selection {
+        Some(0) => return Some((path.clone(), None)),
+        Some(1) => (ConvertMode::Remux, None),
+        Some(2) => {
+            let mut heights = vec![String::from("Keep source resolution")];
+            heights.extend(HEIGHTS.iter().map(|h| format!("{}p", h)));
+            let height = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt("Maximum resolution")
+                .items(&heights)
+                .default(0)
+                .interact()
+                .unwrap();
+            (
+                ConvertMode::Transcode,
+                if height == 0 {
+                    None
+                } else {
+                    Some(HEIGHTS[height - 1])
+                },
+            )
+        }
+        _ => return None,
+    };
+
+    match convert(path, mode, info, max_height) {
+        Ok(converted) => {
+            if let Ok(converted_info) = super::probe::probe(&converted.path) {
+                super::probe::print_media_info(&converted.path, &converted_info);
+            }
+            Some((converted.path.clone(), Some(converted)))
+        }
+        Err(e) => {
+            error!("Error converting {}: {}", path, e);
+            None
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use livepeer_rs::vod::Vod;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+// Hashes of uploaded files are indexed per env in $HOME/.studio/hashes/$ENV.json
+const HASHES_DIR: &str = "hashes";
+
+static INDEX_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+// sha256 -> asset of the assets already on Studio, fetched once per run
+static REMOTE_HASHES: std::sync::Mutex<Option<HashMap<String, Duplicate>>> =
+    std::sync::Mutex::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicateAction {
+    /// Ask whether to upload again
+    #[default]
+    Ask,
+    /// Warn and upload again
+    Warn,
+    /// Skip the upload and return the existing asset
+    Skip,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Duplicate {
+    pub asset_id: String,
+    pub playback_id: String,
+    pub name: String,
+}
+
+fn index_filename() -> String {
+    format!("{}.json", crate::auth::current_env())
+}
+
+fn load_index() -> HashMap<String, Duplicate> {
+    crate::auth::get_string_from_disk(&String::from(HASHES_DIR), &index_filename())
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(index: &HashMap<String, Duplicate>) {
+    crate::auth::save_string_to_disk(
+        &String::from(HASHES_DIR),
+        &index_filename(),
+        &serde_json::to_string_pretty(index).unwrap(),
+    );
+}
+
+pub fn hash_file(path: &String) -> Result<String, String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let size = file.metadata().map_err(|e| e.to_string())?.len();
+    let pb = super::progress::new_bytes_bar(size, path);
+
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let n = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        pb.inc(n as u64);
+    }
+    pb.finish_and_clear();
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn remote_hashes(client: &livepeer_rs::Livepeer) -> HashMap<String, Duplicate> {
+    let mut remote = REMOTE_HASHES.lock().unwrap();
+    if remote.is_none() {
+        let mut hashes = HashMap::new();
+        match client.asset.list_paginated_assets(10000, 0, true) {
+            Ok(list) => {
+                for asset in list.as_array().unwrap_or(&vec![]) {
+                    for hash in asset["hash"].as_array().unwrap_or(&vec![]) {
+                        if hash["algorithm"].as_str() == Some("sha256") {
+                            hashes.insert(
+                                hash["hash"].as_str().unwrap_or("").to_string(),
+                                Duplicate {
+                                    asset_id: asset["id"].as_str().unwrap_or("").to_string(),
+                                    playback_id: asset["playbackId"]
+                                        .as_str()
+                                        .unwrap_or("")
+                                        .to_string(),
+                                    name: asset["name"].as_str().unwrap_or("").to_string(),
+                                },
+                            );
+                        }
+                    }
+                }
+            }
+            Err(e) => warn!("Unable to list assets to look for duplicates: {:?}", e),
+        }
+        *remote = Some(hashes);
+    }
+    remote.clone().unwrap()
+}
+
+// Look for an asset with the same content, first in the local index then on Studio
+pub fn find_duplicate(client: &livepeer_rs::Livepeer, hash: &String) -> Option<Duplicate> {
+    let local = {
+        let _lock = INDEX_LOCK.lock().unwrap();
+        load_index().get(hash).cloned()
+    };
+    if let Some(d) = local {
+        // The asset may have been deleted since it was uploaded
+        if client.asset.get_asset_by_id(d.asset_id.clone()).is_ok() {
+            return Some(d);
+        }
+        let _lock = INDEX_LOCK.lock().unwrap();
+        let mut index = load_index();
+        index.remove(hash);
+        save_index(&index);
+    }
+    remote_hashes(client).get(hash).cloned()
+}
+
+pub fn record_upload(hash: &String, duplicate: Duplicate) {
+    let _lock = INDEX_LOCK.lock().unwrap();
+    let mut index = load_index();
+    index.insert(hash.clone(), duplicate);
+    save_index(&index);
+}
+
+// Decide whether a file whose content already exists on Studio should be uploaded again
+pub fn should_upload(path: &String, duplicate: &Duplicate, action: DuplicateAction) -> bool {
+    let message = format!(
+        "{} was already uploaded as asset {} ({}, playback ID {})",
+        path, duplicate.asset_id, duplicate.name, duplicate.playback_id
+    );
+    match action {
+        DuplicateAction::Ask => dialoguer::Confirm::new()
+            .with_prompt(format!("{}. Upload it again?", message))
+            .default(false)
+            .interact()
+            .unwrap(),
+        DuplicateAction::Warn => {
+            warn!("{}, uploading again", message);
+            true
+        }
+        DuplicateAction::Skip => {
+            warn!("{}, skipping", message);
+            false
+        }
+    }
+}
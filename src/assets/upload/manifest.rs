@@ -0,0 +1,325 @@
+use serde::{Deserialize, Serialize};
+
+// Columns of a csv manifest with a meaning, any other column is kept as metadata
+const CSV_COLUMNS: &'static [&'static str] = &["url", "name", "policy", "metadata"];
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    pub url: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    // public, jwt or webhook:<webhook id>, defaults to the policy of the whole import
+    #[serde(default)]
+    pub policy: Option<String>,
+    // Not sent to Studio, copied to the results manifest to match assets to source records
+    #[serde(default)]
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub url: String,
+    pub name: String,
+    // imported (not tracked), completed or failed
+    pub status: String,
+    pub asset_id: Option<String>,
+    pub task_id: Option<String>,
+    pub playback_id: Option<String>,
+    pub error: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+fn is_csv(path: &String) -> bool {
+    path.to_lowercase().ends_with(".csv")
+}
+
+// Read a csv manifest with a header row, or a json array / one json object per line
+pub fn read_manifest(path: &String) -> Result<Vec<ManifestEntry>, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+
+    if is_csv(path) {
+        let mut reader = csv::Reader::from_reader(contents.as_bytes());
+        let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+        if !headers.iter().any(|h| h == "url") {
+            return Err(format!("{} has no url column", path));
+        }
+        let mut entries = vec![];
+        for (line, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| e.to_string())?;
+            let column = |name: &str| {
+                headers
+                    .iter()
+                    .position(|h| h == name)
+                    .and_then(|i| record.get(i))
+                    .filter(|v| !v.is_empty())
+                    .map(String::from)
+            };
+            let mut metadata = match column("metadata") {
+                Some(m) => serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&m)
+                    .map_err(|e| format!("Invalid metadata on row {}: {}", line + 1, e))?,
+                None => serde_json::Map::new(),
+            };
+            for (header, value) in headers.iter().zip(record.iter()) {
+                if !CSV_COLUMNS.contains(&header) && !value.is_empty() {
+                    metadata.insert(header.to_string(), serde_json::Value::from(value));
+                }
+            }
+            entries.push(ManifestEntry {
+                url: column("url").ok_or(format!("Missing url on row {}", line + 1))?,
+                name: column("name"),
+                policy: column("policy"),
+                metadata: Some(serde_json::Value::Object(metadata))
+                    .filter(|m| m != &serde_json::json!({})),
+            });
+        }
+        return Ok(entries);
+    }
+
+    if contents.trim_start().starts_with("[") {
+        return serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid manifest {}: {}", path, e));
+    }
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            serde_json::from_str(l).map_err(|e| format!("Invalid manifest line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+fn default_name(url: &String) -> String {
+    surf::Url::parse(url)
+        .ok()
+        .and_then(|u| u.path_segments()?.last().map(String::from))
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| nanoid::nanoid!(10))
+}
+
+fn import_entry(
+    client: &livepeer_rs::Livepeer,
+    entry: &ManifestEntry,
+    default_policy: &Option<serde_json::Value>,
+    retry: &super::retry::RetryPolicy,
+    track: bool,
+    preflight: super::probe::PreflightAction,
+) -> ImportResult {
+    let name = entry
+        .name
+        .clone()
+        .unwrap_or_else(|| default_name(&entry.url));
+    let mut result = ImportResult {
+        url: entry.url.clone(),
+        name: name.clone(),
+        status: String::from("failed"),
+        asset_id: None,
+        task_id: None,
+        playback_id: None,
+        error: None,
+        metadata: entry.metadata.clone(),
+    };
+
+    let playback_policy = match &entry.policy {
+        Some(p) => match crate::accesscontrol::parse_playback_policy(p) {
+            Ok(p) => p,
+            Err(e) => {
+                result.error = Some(e);
+                return result;
+            }
+        },
+        None => default_policy.clone(),
+    };
+
+    // Only problems are reported, a full report per url would flood the output
+    if preflight != super::probe::PreflightAction::Off {
+        let report = super::urlcheck::check_url(&entry.url);
+        for warning in &report.warnings {
+            warn!("{}: {}", entry.url, warning);
+        }
+        if !report.errors.is_empty() {
+            let errors = report.errors.join(", ");
+            if preflight == super::probe::PreflightAction::Reject {
+                result.error = Some(format!("Rejected by the url check: {}", errors));
+                return result;
+            }
+            warn!("{}: {}", entry.url, errors);
+        }
+    }
+
+    let imported = retry.run("Importing asset", || {
+        super::import_url(&entry.url, &name, &playback_policy)
+    });
+    let imported = match imported {
+        Ok(i) => i,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+    result.asset_id = imported["asset"]["id"].as_str().map(String::from);
+    result.task_id = imported["task"]["id"].as_str().map(String::from);
+    result.playback_id = imported["asset"]["playbackId"].as_str().map(String::from);
+
+    let task_id = match (&result.task_id, track) {
+        (Some(t), true) => t.clone(),
+        _ => {
+            result.status = String::from("imported");
+            return result;
+        }
+    };
+    let pb = super::progress::new_task_bar(&name);
+    match crate::tasks::wait_for_task(client, &task_id, &pb) {
+        Ok(_) => result.status = String::from("completed"),
+        Err(e) => result.error = Some(e),
+    }
+    result
+}
+
+// Import every entry at most `concurrency` at a time, keeping the manifest order in the results
+pub fn import_manifest(
+    client: &livepeer_rs::Livepeer,
+    entries: Vec<ManifestEntry>,
+    default_policy: Option<serde_json::Value>,
+    concurrency: usize,
+    retry: &super::retry::RetryPolicy,
+    track: bool,
+    preflight: super::probe::PreflightAction,
+) -> Vec<ImportResult> {
+    let queue = std::sync::Mutex::new(entries.into_iter().enumerate().rev().collect::<Vec<_>>());
+    let results = std::sync::Mutex::new(vec![]);
+
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency.max(1) {
+            let worker_client = client.clone();
+            let queue = &queue;
+            let results = &results;
+            let default_policy = &default_policy;
+            scope.spawn(move || loop {
+                let next = queue.lock().unwrap().pop();
+                let (index, entry) = match next {
+                    Some(n) => n,
+                    None => break,
+                };
+                let result = import_entry(
+                    &worker_client,
+                    &entry,
+                    default_policy,
+                    retry,
+                    track,
+                    preflight,
+                );
+                if let Some(e) = &result.error {
+                    error!("Import of {} failed: {}", entry.url, e);
+                }
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+pub fn to_value(results: &Vec<ImportResult>) -> serde_json::Value {
+    serde_json::to_value(results).unwrap()
+}
+
+// Write the results as csv or json depending on the extension, metadata is a json column in csv
+pub fn write_results(path: &String, results: &Vec<ImportResult>) -> Result<(), String> {
+    let contents = if is_csv(path) {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        writer
+            .write_record(&[
+                "url",
+                "name",
+                "status",
+                "assetId",
+                "taskId",
+                "playbackId",
+                "error",
+                "metadata",
+            ])
+            .map_err(|e| e.to_string())?;
+        for r in results {
+            writer
+                .write_record(&[
+                    r.url.clone(),
+                    r.name.clone(),
+                    r.status.clone(),
+                    r.asset_id.clone().unwrap_or_default(),
+                    r.task_id.clone().unwrap_or_default(),
+                    r.playback_id.clone().unwrap_or_default(),
+                    r.error.clone().unwrap_or_default(),
+                    r.metadata.as_ref().map_or(String::new(), |m| m.to_string()),
+                ])
+                .map_err(|e| e.to_string())?;
+        }
+        String::from_utf8(writer.into_inner().map_err(|e| e.to_string())?).unwrap()
+    } else {
+        serde_json::to_string_pretty(results).unwrap()
+    };
+    std::fs::write(path, contents).map_err(|e| format!("Error writing {}: {}", path, e))
+}
+
+pub fn bulk_import(client: &livepeer_rs::Livepeer) {
+    let path = dialoguer::Input::<String>::new()
+        .with_prompt("Enter the path of a csv or json manifest (url, name, policy, metadata)")
+        .interact()
+        .unwrap();
+    let entries = match read_manifest(&path) {
+        Ok(e) => e,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+    if entries.is_empty() {
+        warn!("No entries in {}", path);
+        return;
+    }
+    println!("Found {} urls to import", entries.len());
+
+    let concurrency = dialoguer::Input::<usize>::new()
+        .with_prompt("How many imports to run at the same time?")
+        .default(4)
+        .interact()
+        .unwrap();
+    let track = dialoguer::Confirm::new()
+        .with_prompt("Wait for every import task to complete?")
+        .default(true)
+        .interact()
+        .unwrap();
+    let results_path = dialoguer::Input::<String>::new()
+        .with_prompt("Write the results to")
+        .default(String::from("import-results.json"))
+        .interact()
+        .unwrap();
+    println!("Playback policy for entries without one");
+    let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
+
+    let results = import_manifest(
+        client,
+        entries,
+        playback_policy,
+        concurrency,
+        &super::retry::RetryPolicy::default(),
+        track,
+        super::probe::PreflightAction::Warn,
+    );
+    println!(
+        "{}",
+        crate::output::render(
+            &to_value(&results),
+            crate::output::Resource::Import,
+            crate::output::OutputFormat::Table
+        )
+    );
+    match write_results(&results_path, &results) {
+        Ok(_) => println!("Results written to {}", results_path),
+        Err(e) => error!("{}", e),
+    }
+}
@@ -1,17 +1,64 @@
-use livepeer_rs::vod::Vod;
-
+use serde::{Deserialize, Serialize};
+
+pub mod batch;
+pub mod convert;
+pub mod dedup;
+pub mod manifest;
+pub mod probe;
+pub mod progress;
+pub mod queue;
 pub mod resumable;
+pub mod retry;
+pub mod stream;
+pub mod urlcheck;
+pub mod watch;
+
+pub const VIDEO_EXTENSIONS: &'static [&'static str] = &[
+    "mp4", "mkv", "avi", "mov", "flv", "wmv", "webm", "mpeg", "mpg", "m4v", "3gp",
+];
+
 pub struct UploadAssetResult {
     pub asset_id: String,
     pub task_id: String,
     pub playback_id: String,
+    // Set when the content was already on Studio and the existing asset is returned, without a task
+    pub duplicate: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UploadOptions {
+    pub resumable: bool,
+    pub on_duplicate: dedup::DuplicateAction,
+    pub preflight: probe::PreflightAction,
+    pub retry: retry::RetryPolicy,
+}
+
+impl UploadOptions {
+    // Prompts can't be answered while several uploads draw progress bars at once: a duplicate is
+    // skipped and a file with problems is uploaded with a warning instead
+    pub fn unattended(&self) -> UploadOptions {
+        let mut options = self.clone();
+        if options.on_duplicate == dedup::DuplicateAction::Ask {
+            options.on_duplicate = dedup::DuplicateAction::Skip;
+        }
+        if options.preflight == probe::PreflightAction::Ask {
+            options.preflight = probe::PreflightAction::Warn;
+        }
+        options
+    }
 }
 
 pub fn upload_asset(client: &livepeer_rs::Livepeer) -> Option<UploadAssetResult> {
     let mut result = None;
     // Choose type of upload
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .items(&["Upload from URL", "Upload from File", "< Back"])
+        .items(&[
+            "Upload from URL",
+            "Upload from File",
+            "Bulk Import from Manifest (csv or json)",
+            "< Back",
+        ])
         .default(0)
         .interact_on_opt(&crate::Term::stderr())
         .unwrap();
@@ -25,6 +72,9 @@ pub fn upload_asset(client: &livepeer_rs::Livepeer) -> Option<UploadAssetResult>
                 result = upload_from_file(client);
             }
             if index == 2 {
+                manifest::bulk_import(client);
+            }
+            if index == 3 {
                 super::assets(client);
             }
         }
@@ -42,6 +92,10 @@ pub fn upload_from_url(client: &livepeer_rs::Livepeer) -> Option<UploadAssetResu
         .interact()
         .unwrap();
 
+    if !urlcheck::preflight(&url, probe::PreflightAction::Ask) {
+        return None;
+    }
+
     let mut asset_name = dialoguer::Input::<String>::new()
         .with_prompt("Enter name for asset (blank for random id)")
         .default("livepeer_rs_import".to_string())
@@ -50,7 +104,9 @@ pub fn upload_from_url(client: &livepeer_rs::Livepeer) -> Option<UploadAssetResu
 
     let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
 
-    let up_result = client.asset.import_asset(url, asset_name, playback_policy);
+    let up_result = retry::RetryPolicy::default().run("Importing asset", || {
+        import_url(&url, &asset_name, &playback_policy)
+    });
 
     if let Ok(a) = up_result {
         let asset_id = Some(a["asset"]["id"].as_str().unwrap().to_string());
@@ -60,10 +116,11 @@ pub fn upload_from_url(client: &livepeer_rs::Livepeer) -> Option<UploadAssetResu
             asset_id: asset_id.unwrap(),
             task_id: task_id.unwrap(),
             playback_id: playback_id.unwrap(),
+            duplicate: false,
         });
         println!("Asset uploaded: {:?}", a);
     } else {
-        error!("Error uploading asset: {:?}", up_result);
+        error!("Error uploading asset: {}", up_result.unwrap_err());
     }
     return result;
 }
@@ -72,7 +129,13 @@ pub fn upload_from_file(client: &livepeer_rs::Livepeer) -> Option<UploadAssetRes
     let mut result = None;
     // Choose between direct and resumable upload
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
-        .items(&["Direct Upload", "Resumable Upload (TODO)", "< Back"])
+        .items(&[
+            "Direct Upload",
+            "Resumable Upload",
+            "Bulk Upload (folder or glob)",
+            "Watch Folder (upload new files)",
+            "< Back",
+        ])
         .default(0)
         .interact_on_opt(&crate::Term::stderr())
         .unwrap();
@@ -85,12 +148,30 @@ pub fn upload_from_file(client: &livepeer_rs::Livepeer) -> Option<UploadAssetRes
                     .to_str()
                     .unwrap()
                     .to_string();
-                result = do_upload(client, &current_folder_string, false);
+                result = do_upload(client, &current_folder_string, &UploadOptions::default());
             }
             if index == 1 {
-                println!("Not implemented yet, use direct uploads for now");
+                let current_folder_string = std::env::current_dir()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                result = do_upload(
+                    client,
+                    &current_folder_string,
+                    &UploadOptions {
+                        resumable: true,
+                        ..Default::default()
+                    },
+                );
             }
             if index == 2 {
+                batch::bulk_upload(client);
+            }
+            if index == 3 {
+                watch::watch(client);
+            }
+            if index == 4 {
                 upload_asset(client);
             }
         }
@@ -104,7 +185,7 @@ pub fn upload_from_file(client: &livepeer_rs::Livepeer) -> Option<UploadAssetRes
 pub fn do_upload(
     client: &livepeer_rs::Livepeer,
     current_folder_string: &String,
-    resumable: bool,
+    options: &UploadOptions,
 ) -> Option<UploadAssetResult> {
     // read from disk recent-uploads
     let mut result = None;
@@ -135,11 +216,11 @@ pub fn do_upload(
                     .to_str()
                     .unwrap()
                     .to_string();
-                do_upload(client, &parent_folder, resumable);
+                do_upload(client, &parent_folder, options);
             } else {
                 if files[index].ends_with("/") {
                     let new_folder = files[index].clone();
-                    do_upload(client, &new_folder, resumable);
+                    do_upload(client, &new_folder, options);
                 } else {
                     let mut path_of_file = &files[index];
                     let file_name = std::path::Path::new(path_of_file)
@@ -173,72 +254,30 @@ pub fn do_upload(
 
                     let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
 
-                    info!("Generating presigned urls");
-
-                    let urls = client.asset.get_presigned_url(asset_name, playback_policy);
-
-                    match urls {
-                        Ok(urls) => {
-                            // create indicatif spinner
-                            let spinner = indicatif::ProgressBar::new_spinner();
-                            spinner.enable_steady_tick(std::time::Duration::from_millis(120));
-                            spinner.set_style(
-                                indicatif::ProgressStyle::with_template("{spinner:.blue} {msg}")
-                                    .unwrap()
-                                    // For more spinners check out the cli-spinners project:
-                                    // https://github.com/sindresorhus/cli-spinners/blob/master/spinners.json
-                                    .tick_strings(&[
-                                        "▹▹▹▹▹",
-                                        "▸▹▹▹▹",
-                                        "▹▸▹▹▹",
-                                        "▹▹▸▹▹",
-                                        "▹▹▹▸▹",
-                                        "▹▹▹▹▸",
-                                        "▪▪▪▪▪",
-                                    ]),
-                            );
-                            spinner.set_message("Uploading...");
-                            let upload_url = String::from(urls["url"].as_str().unwrap());
-                            // get absolute path of file
-                            let path_of_file = std::path::Path::new(path_of_file)
-                                .canonicalize()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_string();
-
-                            let mut up_result = Err(livepeer_rs::errors::Error::UNKNOWN);
-                            if resumable {
-                                error!("Resumable upload not implemented yet in livepeer_rs!")
-                            } else {
-                                up_result = client.asset.upload_asset(upload_url, path_of_file);
-                            }
-
-                            spinner.finish();
-
-                            match up_result {
-                                Ok(_) => {
-                                    let asset_id =
-                                        urls["asset"]["id"].as_str().unwrap().to_string();
-                                    let task_id = urls["task"]["id"].as_str().unwrap().to_string();
-                                    let playback_id =
-                                        urls["asset"]["playbackId"].as_str().unwrap().to_string();
-                                    result = Some(UploadAssetResult {
-                                        asset_id,
-                                        task_id,
-                                        playback_id,
-                                    });
-                                    info!("Upload successful");
-                                }
-                                Err(e) => {
-                                    error!("Error: {:?}", e);
+                    // Inspect the file here so a problematic one can be fixed before uploading
+                    let mut options = options.clone();
+                    let mut converted = None;
+                    if options.preflight != probe::PreflightAction::Off {
+                        if let Ok(info) = probe::probe(path_of_file) {
+                            probe::print_media_info(path_of_file, &info);
+                            if !info.issues.is_empty() {
+                                match convert::prepare(path_of_file, &info) {
+                                    Some((_, c)) => converted = c,
+                                    None => return None,
                                 }
                             }
-                        }
-                        Err(e) => {
-                            error!("Error: {:?}", e);
+                            options.preflight = probe::PreflightAction::Off;
                         }
                     }
+
+                    result = queue::upload_converted(
+                        client,
+                        path_of_file,
+                        converted,
+                        asset_name,
+                        playback_policy,
+                        options,
+                    );
                 }
             }
         }
@@ -249,6 +288,218 @@ pub fn do_upload(
     return result;
 }
 
+// `source` is the file hashed for duplicate detection, the original when uploading a converted copy
+pub fn upload_file(
+    client: &livepeer_rs::Livepeer,
+    path_of_file: &String,
+    source: &String,
+    asset_name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: &UploadOptions,
+) -> Result<UploadAssetResult, String> {
+    // get absolute path of file
+    let path_of_file = std::path::Path::new(path_of_file)
+        .canonicalize()
+        .map_err(|e| format!("Error reading {}: {}", path_of_file, e))?
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    if options.resumable {
+        let saved = resumable::load_session(&path_of_file);
+        if let Some(expired) = saved.as_ref().filter(|s| resumable::session_expired(s)) {
+            warn!(
+                "The upload of {} for asset {} expired, starting a new upload",
+                path_of_file, expired.asset_id
+            );
+            resumable::remove_session(&path_of_file);
+        } else if let Some(mut session) = saved {
+            info!(
+                "Resuming upload of {} for asset {}",
+                path_of_file, session.asset_id
+            );
+            upload_resumable(&mut session, &options.retry)?;
+            match dedup::hash_file(source) {
+                Ok(hash) => dedup::record_upload(
+                    &hash,
+                    dedup::Duplicate {
+                        asset_id: session.asset_id.clone(),
+                        playback_id: session.playback_id.clone(),
+                        name: asset_name,
+                    },
+                ),
+                Err(e) => warn!("Not indexing {} for duplicate detection: {}", source, e),
+            }
+            info!("Upload successful");
+            return Ok(UploadAssetResult {
+                asset_id: session.asset_id,
+                task_id: session.task_id,
+                playback_id: session.playback_id,
+                duplicate: false,
+            });
+        }
+    }
+
+    if !probe::preflight(&path_of_file, options.preflight) {
+        return Err(String::from("Rejected by the media inspection"));
+    }
+
+    let hash = dedup::hash_file(source).map_err(|e| format!("Error reading {}: {}", source, e))?;
+    if let Some(d) = dedup::find_duplicate(client, &hash) {
+        if !dedup::should_upload(source, &d, options.on_duplicate) {
+            return Ok(UploadAssetResult {
+                asset_id: d.asset_id,
+                task_id: String::new(),
+                playback_id: d.playback_id,
+                duplicate: true,
+            });
+        }
+    }
+
+    info!("Generating presigned urls");
+
+    let urls = options.retry.run("Generating presigned urls", || {
+        request_upload(&asset_name, &playback_policy)
+    })?;
+
+    let asset_id = urls["asset"]["id"].as_str().unwrap().to_string();
+    let task_id = urls["task"]["id"].as_str().unwrap().to_string();
+    let playback_id = urls["asset"]["playbackId"].as_str().unwrap().to_string();
+
+    if options.resumable {
+        let tus_endpoint = urls["tusEndpoint"].as_str().unwrap_or("");
+        let mut session = options.retry.run("Creating tus upload", || {
+            resumable::create_session(
+                tus_endpoint,
+                &path_of_file,
+                asset_id.clone(),
+                task_id.clone(),
+                playback_id.clone(),
+            )
+        })?;
+        upload_resumable(&mut session, &options.retry)?;
+    } else {
+        let upload_url = String::from(urls["url"].as_str().unwrap_or(""));
+        options
+            .retry
+            .run("Uploading file", || progress::put_file(&upload_url, &path_of_file))?;
+    }
+
+    dedup::record_upload(
+        &hash,
+        dedup::Duplicate {
+            asset_id: asset_id.clone(),
+            playback_id: playback_id.clone(),
+            name: asset_name,
+        },
+    );
+    info!("Upload successful");
+    Ok(UploadAssetResult {
+        asset_id,
+        task_id,
+        playback_id,
+        duplicate: false,
+    })
+}
+
+// Every tus retry resumes from the offset the server has, and a session that still fails is
+// kept on disk so the upload can be resumed later
+fn upload_resumable(
+    session: &mut resumable::ResumableSession,
+    retry: &retry::RetryPolicy,
+) -> Result<(), String> {
+    retry
+        .run("Uploading file", || resumable::upload(session))
+        .map_err(|e| {
+            format!(
+                "{} ({} of {} bytes uploaded, retry the upload to resume it)",
+                e, session.offset, session.size
+            )
+        })
+}
+
+fn upload_body(name: &String, playback_policy: &Option<serde_json::Value>) -> serde_json::Value {
+    let mut body = serde_json::json!({ "name": name });
+    if let Some(policy) = playback_policy {
+        body["playbackPolicy"] = policy.clone();
+    }
+    body
+}
+
+// Upload urls (PUT and tus) of a new asset
+pub fn request_upload(
+    name: &String,
+    playback_policy: &Option<serde_json::Value>,
+) -> Result<serde_json::Value, retry::Error> {
+    crate::api::send(
+        surf::http::Method::Post,
+        "/api/asset/request-upload",
+        Some(upload_body(name, playback_policy)),
+    )
+}
+
+// Import an asset from a url. The POST creates the asset, so one that timed out may have
+// created it anyway: only rate limits and an unavailable API, which reject the request before
+// it is handled, are retried.
+pub fn import_url(
+    url: &String,
+    name: &String,
+    playback_policy: &Option<serde_json::Value>,
+) -> Result<serde_json::Value, retry::Error> {
+    let mut body = upload_body(name, playback_policy);
+    body["url"] = serde_json::json!(url);
+    crate::api::send(surf::http::Method::Post, "/api/asset/upload/url", Some(body)).map_err(|e| {
+        match e.status {
+            Some(429) | Some(503) => e,
+            Some(_) => retry::Error::fatal(e.message),
+            None => retry::Error::fatal(format!(
+                "{}, the asset may have been created anyway, check the asset list before importing again",
+                e.message
+            )),
+        }
+    })
+}
+
+// Parse an upload url or tus endpoint returned by Studio, surf panics on one it can't parse
+pub fn parse_url(url: &str, what: &str) -> Result<surf::Url, retry::Error> {
+    surf::Url::parse(url)
+        .map_err(|e| retry::Error::fatal(format!("Invalid {} {:?}: {}", what, url, e)))
+}
+
+// MIME type sent with a tus upload, from the extension of the file
+pub fn content_type(file_name: &str) -> &'static str {
+    let extension = std::path::Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("mp4") | Some("m4v") => "video/mp4",
+        Some("mkv") => "video/x-matroska",
+        Some("avi") => "video/x-msvideo",
+        Some("mov") => "video/quicktime",
+        Some("flv") => "video/x-flv",
+        Some("wmv") => "video/x-ms-wmv",
+        Some("webm") => "video/webm",
+        Some("mpeg") | Some("mpg") => "video/mpeg",
+        Some("3gp") => "video/3gpp",
+        Some("ts") => "video/mp2t",
+        _ => "application/octet-stream",
+    }
+}
+
+// Visible file with one of the video extensions
+pub fn is_video_file(path: &std::path::Path) -> bool {
+    let hidden = path
+        .file_name()
+        .and_then(|f| f.to_str())
+        .map_or(true, |f| f.starts_with("."));
+    let video = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()));
+    path.is_file() && !hidden && video
+}
+
 pub fn list_files_and_folders(path: &String, recents: Option<String>) -> Vec<String> {
     let mut files = vec![];
 
@@ -263,10 +514,6 @@ pub fn list_files_and_folders(path: &String, recents: Option<String>) -> Vec<Str
             }
             files.push(format!("{}/", path_string));
         } else {
-            let video_extensions = vec![
-                "mp4", "mkv", "avi", "mov", "flv", "wmv", "webm", "mpeg", "mpg", "m4v", "3gp",
-            ];
-
             // if file has extension
 
             let extension = path.extension();
@@ -284,7 +531,7 @@ pub fn list_files_and_folders(path: &String, recents: Option<String>) -> Vec<Str
                 continue;
             }
 
-            if !video_extensions.contains(&ext.unwrap()) {
+            if !VIDEO_EXTENSIONS.contains(&ext.unwrap()) {
                 continue;
             }
 
@@ -0,0 +1,232 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+const SUPPORTED_VIDEO_CODECS: &'static [&'static str] = &["h264", "hevc", "vp8", "vp9", "av1"];
+const SUPPORTED_AUDIO_CODECS: &'static [&'static str] =
+    &["aac", "mp3", "opus", "vorbis", "ac3", "eac3"];
+const SUPPORTED_PIXEL_FORMATS: &'static [&'static str] = &["yuv420p", "yuvj420p", "nv12"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PreflightAction {
+    /// Ask whether to upload a file with problems
+    #[default]
+    Ask,
+    /// Show problems and upload anyway
+    Warn,
+    /// Don't upload files with problems
+    Reject,
+    /// Don't inspect files
+    Off,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub container: String,
+    pub duration: Option<f64>,
+    pub bitrate: Option<u64>,
+    pub video_codec: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub fps: Option<f64>,
+    pub pixel_format: Option<String>,
+    pub variable_frame_rate: bool,
+    pub interlaced: bool,
+    pub audio_codec: Option<String>,
+    pub audio_channels: Option<u64>,
+    pub issues: Vec<String>,
+}
+
+// Parse ffprobe rationals like 30000/1001
+fn parse_rate(rate: Option<&str>) -> Option<f64> {
+    let (num, den) = rate?.split_once('/')?;
+    let (num, den) = (num.parse::<f64>().ok()?, den.parse::<f64>().ok()?);
+    if den == 0.0 || num == 0.0 {
+        return None;
+    }
+    Some(num / den)
+}
+
+pub fn probe(path: &String) -> Result<MediaInfo, String> {
+    let ffprobe = crate::live::get_ffprobe_path()?;
+    let output = std::process::Command::new(ffprobe)
+        .args([
+            "-v",
+            "error",
+            "-print_format",
+            "json",
+            "-show_format",
+            "-show_streams",
+        ])
+        .arg(path)
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffprobe failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let probe: serde_json::Value =
+        serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())?;
+
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let video = streams
+        .iter()
+        .find(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"] != 1);
+    let audio = streams.iter().find(|s| s["codec_type"] == "audio");
+
+    let mut info = MediaInfo {
+        container: probe["format"]["format_name"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+        duration: probe["format"]["duration"]
+            .as_str()
+            .and_then(|d| d.parse().ok()),
+        bitrate: probe["format"]["bit_rate"]
+            .as_str()
+            .and_then(|b| b.parse().ok()),
+        ..Default::default()
+    };
+
+    if let Some(v) = video {
+        let real_rate = parse_rate(v["r_frame_rate"].as_str());
+        let avg_rate = parse_rate(v["avg_frame_rate"].as_str());
+        info.video_codec = v["codec_name"].as_str().map(String::from);
+        info.width = v["width"].as_u64();
+        info.height = v["height"].as_u64();
+        info.fps = avg_rate.or(real_rate);
+        info.pixel_format = v["pix_fmt"].as_str().map(String::from);
+        // A container frame rate far from the average one means timestamps are irregular
+        info.variable_frame_rate = match (real_rate, avg_rate) {
+            (Some(r), Some(a)) => (r - a).abs() / r > 0.01,
+            _ => false,
+        };
+        info.interlaced = !matches!(
+            v["field_order"].as_str(),
+            None | Some("progressive") | Some("unknown")
+        );
+    }
+    if let Some(a) = audio {
+        info.audio_codec = a["codec_name"].as_str().map(String::from);
+        info.audio_channels = a["channels"].as_u64();
+    }
+
+    info.issues = issues(&info);
+    Ok(info)
+}
+
+fn issues(info: &MediaInfo) -> Vec<String> {
+    let mut issues = vec![];
+    match &info.video_codec {
+        None => issues.push(String::from("No video stream")),
+        Some(c) if !SUPPORTED_VIDEO_CODECS.contains(&c.as_str()) => issues.push(format!(
+            "Video codec {} is not supported, use one of {}",
+            c,
+            SUPPORTED_VIDEO_CODECS.join(", ")
+        )),
+        _ => {}
+    }
+    match &info.audio_codec {
+        None => issues.push(String::from("No audio stream")),
+        Some(c) if !SUPPORTED_AUDIO_CODECS.contains(&c.as_str()) => issues.push(format!(
+            "Audio codec {} is not supported, use one of {}",
+            c,
+            SUPPORTED_AUDIO_CODECS.join(", ")
+        )),
+        _ => {}
+    }
+    if let Some(p) = &info.pixel_format {
+        if !SUPPORTED_PIXEL_FORMATS.contains(&p.as_str()) {
+            issues.push(format!(
+                "Pixel format {} may not be supported, prefer yuv420p",
+                p
+            ));
+        }
+    }
+    if info.variable_frame_rate {
+        issues.push(String::from("Variable frame rate"));
+    }
+    if info.interlaced {
+        issues.push(String::from("Interlaced video"));
+    }
+    if let (Some(w), Some(h)) = (info.width, info.height) {
+        if w % 2 != 0 || h % 2 != 0 {
+            issues.push(format!("Odd resolution {}x{}", w, h));
+        }
+        if w * h > 3840 * 2160 {
+            issues.push(format!("Resolution {}x{} is above 4K", w, h));
+        }
+    }
+    if info.duration.is_none() {
+        issues.push(String::from("Unknown duration"));
+    }
+    issues
+}
+
+pub fn print_media_info(path: &String, info: &MediaInfo) {
+    let na = String::from("-");
+    eprintln!("{}", path.cyan().bold());
+    eprintln!("  Container:  {}", info.container);
+    eprintln!(
+        "  Video:      {} {}x{} @ {} fps{}",
+        info.video_codec.as_ref().unwrap_or(&na),
+        info.width.map_or(na.clone(), |w| w.to_string()),
+        info.height.map_or(na.clone(), |h| h.to_string()),
+        info.fps.map_or(na.clone(), |f| format!("{:.2}", f)),
+        info.pixel_format
+            .as_ref()
+            .map_or(String::new(), |p| format!(" ({})", p)),
+    );
+    eprintln!(
+        "  Audio:      {}{}",
+        info.audio_codec.as_ref().unwrap_or(&na),
+        info.audio_channels
+            .map_or(String::new(), |c| format!(" ({} channels)", c)),
+    );
+    eprintln!(
+        "  Duration:   {}",
+        info.duration.map_or(na.clone(), |d| format!("{:.1}s", d))
+    );
+    eprintln!(
+        "  Bitrate:    {}",
+        info.bitrate
+            .map_or(na.clone(), |b| format!("{:.0} kbps", b as f64 / 1000.0))
+    );
+    for issue in &info.issues {
+        eprintln!("  {} {}", "⚠".yellow().bold(), issue.yellow());
+    }
+}
+
+// Inspect a file before uploading it, returns whether the upload should go on
+pub fn preflight(path: &String, action: PreflightAction) -> bool {
+    if action == PreflightAction::Off {
+        return true;
+    }
+    let info = match probe(path) {
+        Ok(i) => i,
+        Err(e) => {
+            warn!("Unable to inspect {}: {}", path, e);
+            return true;
+        }
+    };
+    print_media_info(path, &info);
+    if info.issues.is_empty() {
+        return true;
+    }
+
+    match action {
+        PreflightAction::Ask => dialoguer::Confirm::new()
+            .with_prompt("This file may not process correctly. Upload it anyway?")
+            .default(true)
+            .interact()
+            .unwrap(),
+        PreflightAction::Reject => {
+            error!("Not uploading {}, fix the problems above first", path);
+            false
+        }
+        _ => true,
+    }
+}
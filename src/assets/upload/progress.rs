@@ -0,0 +1,126 @@
+use async_std::io::Read;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// Shown after the progress bar while transferring bytes, {msg} is the file name
+pub const BYTES_INFO: &str = "{bytes}/{total_bytes} ({binary_bytes_per_sec}, {eta}) {msg}";
+
+// Shown while transferring bytes of unknown size
+const STREAM_INFO: &str =
+    "{spinner:.green} [{elapsed_precise}] {bytes} ({binary_bytes_per_sec}) {msg}";
+
+// All transfer bars are drawn together so concurrent uploads don't overwrite each other
+static TRANSFERS: std::sync::OnceLock<indicatif::MultiProgress> = std::sync::OnceLock::new();
+
+pub fn new_bytes_bar(size: u64, path: &String) -> indicatif::ProgressBar {
+    let pb = TRANSFERS
+        .get_or_init(indicatif::MultiProgress::new)
+        .add(indicatif::ProgressBar::new(size));
+    pb.set_style(crate::tasks::progress_style(BYTES_INFO));
+    pb.set_message(
+        std::path::Path::new(path)
+            .file_name()
+            .map_or(String::new(), |f| f.to_string_lossy().to_string()),
+    );
+    pb
+}
+
+// Bar for the progress of a task, drawn with the transfers
+pub fn new_task_bar(name: &String) -> indicatif::ProgressBar {
+    let pb = TRANSFERS
+        .get_or_init(indicatif::MultiProgress::new)
+        .add(indicatif::ProgressBar::new(100));
+    pb.set_style(crate::tasks::progress_style("{pos}% {msg}"));
+    pb.set_message(name.clone());
+    pb
+}
+
+// Bar for a transfer of unknown size, e.g. from stdin
+pub fn new_stream_bar(name: &String) -> indicatif::ProgressBar {
+    let pb = TRANSFERS
+        .get_or_init(indicatif::MultiProgress::new)
+        .add(indicatif::ProgressBar::new_spinner());
+    pb.set_style(indicatif::ProgressStyle::with_template(STREAM_INFO).unwrap());
+    pb.set_message(name.clone());
+    pb
+}
+
+// Wraps a reader and advances a progress bar by every byte read from it, holding reads back
+// while the bandwidth limit is exceeded
+pub struct ProgressReader<R> {
+    inner: R,
+    pb: indicatif::ProgressBar,
+    limit: Option<u64>,
+    delay: Option<async_io::Timer>,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, pb: indicatif::ProgressBar) -> ProgressReader<R> {
+        ProgressReader {
+            inner,
+            pb,
+            limit: crate::ratelimit::limit(),
+            delay: None,
+        }
+    }
+}
+
+impl<R: Read + Unpin> Read for ProgressReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        // Sleep without blocking the thread, the http client may poll other transfers on it
+        if let Some(delay) = self.delay.as_mut() {
+            if Pin::new(delay).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.delay = None;
+        }
+
+        let limit = self.limit;
+        let len = limit.map_or(buf.len(), |l| buf.len().min(crate::ratelimit::max_read(l)));
+        let poll = Pin::new(&mut self.inner).poll_read(cx, &mut buf[..len]);
+        if let Poll::Ready(Ok(n)) = &poll {
+            self.pb.inc(*n as u64);
+            if let Some(l) = limit {
+                let wait = crate::ratelimit::reserve(*n, l);
+                if !wait.is_zero() {
+                    self.delay = Some(async_io::Timer::after(wait));
+                }
+            }
+        }
+        poll
+    }
+}
+
+// PUT a file to a presigned upload url, streaming it through a progress bar
+pub fn put_file(upload_url: &str, path: &String) -> Result<(), super::retry::Error> {
+    let url = super::parse_url(upload_url, "upload url")?;
+    let size = std::fs::metadata(path)?.len();
+
+    let pb = new_bytes_bar(size, path);
+    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+
+    let response = async_std::task::block_on(async {
+        let file = async_std::fs::File::open(path).await?;
+        let reader = async_std::io::BufReader::new(ProgressReader::new(file, pb.clone()));
+        surf::RequestBuilder::new(surf::http::Method::Put, url)
+            .body(surf::Body::from_reader(reader, Some(size as usize)))
+            .await
+    })
+    .map_err(|e| super::retry::Error::from(e.to_string()))?;
+
+    if !response.status().is_success() {
+        pb.abandon();
+        return Err(super::retry::Error::http(
+            response.status() as u16,
+            format!("Error uploading file: {}", response.status()),
+        ));
+    }
+
+    pb.finish();
+    Ok(())
+}
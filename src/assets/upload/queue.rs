@@ -0,0 +1,453 @@
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+use super::{UploadAssetResult, UploadOptions};
+
+// The queue lives in $HOME/.studio/queue/uploads.json
+const QUEUE_DIR: &str = "queue";
+const QUEUE_FILE: &str = "uploads.json";
+// Held during read-modify-write cycles of the queue file, so `queue run` and `watch` in two
+// processes don't overwrite each other's changes
+const LOCK_FILE: &str = "uploads.json.lock";
+
+// Serializes read-modify-write cycles of the queue file between upload workers
+static QUEUE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QueueStatus {
+    Pending,
+    InFlight,
+    Failed,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueEntry {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub playback_policy: Option<serde_json::Value>,
+    #[serde(default)]
+    pub options: UploadOptions,
+    pub status: QueueStatus,
+    pub asset_id: Option<String>,
+    pub task_id: Option<String>,
+    pub playback_id: Option<String>,
+    pub error: Option<String>,
+    // Process uploading an in-flight entry, used to detect uploads interrupted by a crash
+    pub pid: Option<u32>,
+    // Start time of that process, a reused pid belongs to a process started later
+    #[serde(default)]
+    pub pid_started: Option<String>,
+    // The path is a converted copy in the temp folder, removed once uploaded or purged
+    #[serde(default)]
+    pub temporary: bool,
+    // File the converted copy was made from, hashed for duplicate detection
+    #[serde(default)]
+    pub source: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn queue_dir() -> std::path::PathBuf {
+    dirs::home_dir().unwrap().join(".studio").join(QUEUE_DIR)
+}
+
+// A missing file is an empty queue, one that can't be read or parsed is an error so it doesn't
+// get overwritten with an empty queue
+fn load() -> Result<Vec<QueueEntry>, String> {
+    let path = queue_dir().join(QUEUE_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents).map_err(|e| {
+            format!(
+                "Unable to parse the upload queue {}: {}, fix or remove it",
+                path.display(),
+                e
+            )
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(format!(
+            "Unable to read the upload queue {}: {}",
+            path.display(),
+            e
+        )),
+    }
+}
+
+// Written to a temp file renamed over the queue, a crash mid-write leaves the previous queue
+fn save(entries: &Vec<QueueEntry>) -> Result<(), String> {
+    let path = queue_dir().join(QUEUE_FILE);
+    let temp = queue_dir().join(format!("{}.{}.tmp", QUEUE_FILE, std::process::id()));
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&temp)?;
+        file.write_all(serde_json::to_string_pretty(entries).unwrap().as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp, &path)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&temp);
+        format!("Unable to save the upload queue {}: {}", path.display(), e)
+    })
+}
+
+fn update<T>(f: impl FnOnce(&mut Vec<QueueEntry>) -> T) -> Result<T, String> {
+    let _lock = QUEUE_LOCK.lock().unwrap();
+    std::fs::create_dir_all(queue_dir()).map_err(|e| e.to_string())?;
+    // Released when the file is closed, also when the process dies
+    let lock_file = std::fs::File::create(queue_dir().join(LOCK_FILE))
+        .and_then(|f| f.lock().map(|_| f))
+        .map_err(|e| format!("Unable to lock the upload queue: {}", e))?;
+    let mut entries = load()?;
+    let result = f(&mut entries);
+    save(&entries)?;
+    drop(lock_file);
+    Ok(result)
+}
+
+// Entries left in flight by a process that is gone were interrupted, mark them failed
+fn recover_interrupted(entries: &mut Vec<QueueEntry>) {
+    for entry in entries.iter_mut() {
+        let uploading = entry.pid.map_or(false, |pid| {
+            crate::process::is_same_process(pid, entry.pid_started.as_deref())
+        });
+        if entry.status == QueueStatus::InFlight && !uploading {
+            warn!("Upload {} of {} was interrupted", entry.id, entry.path);
+            entry.status = QueueStatus::Failed;
+            entry.error = Some(String::from("Interrupted"));
+            entry.pid = None;
+            entry.pid_started = None;
+            entry.updated_at = chrono::Utc::now().timestamp_millis();
+        }
+    }
+}
+
+pub fn list() -> Result<Vec<QueueEntry>, String> {
+    update(|entries| {
+        recover_interrupted(entries);
+        entries.clone()
+    })
+}
+
+pub fn enqueue(
+    path: &String,
+    name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: UploadOptions,
+) -> Result<String, String> {
+    enqueue_entry(path, name, playback_policy, options, None)
+}
+
+fn canonical(path: &String) -> String {
+    std::path::Path::new(path)
+        .canonicalize()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or(path.clone())
+}
+
+fn enqueue_entry(
+    path: &String,
+    name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: UploadOptions,
+    source: Option<&String>,
+) -> Result<String, String> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let entry = QueueEntry {
+        id: nanoid::nanoid!(10),
+        path: canonical(path),
+        name,
+        playback_policy,
+        options,
+        status: QueueStatus::Pending,
+        asset_id: None,
+        task_id: None,
+        playback_id: None,
+        error: None,
+        pid: None,
+        pid_started: None,
+        temporary: source.is_some(),
+        source: source.map(canonical),
+        created_at: now,
+        updated_at: now,
+    };
+    let id = entry.id.clone();
+    update(|entries| entries.push(entry))?;
+    Ok(id)
+}
+
+// Upload a pending entry, recording the outcome in the queue
+pub fn run_entry(client: &livepeer_rs::Livepeer, id: &String) -> Option<UploadAssetResult> {
+    let entry = update(|entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|e| &e.id == id && e.status == QueueStatus::Pending)?;
+        entry.status = QueueStatus::InFlight;
+        entry.pid = Some(std::process::id());
+        entry.pid_started = crate::process::start_time(std::process::id());
+        entry.updated_at = chrono::Utc::now().timestamp_millis();
+        Some(entry.clone())
+    });
+    let entry = match entry {
+        Ok(Some(e)) => e,
+        Ok(None) => {
+            error!("No pending upload {} in the queue", id);
+            return None;
+        }
+        Err(e) => {
+            error!("{}", e);
+            return None;
+        }
+    };
+
+    let result = super::upload_file(
+        client,
+        &entry.path,
+        entry.source.as_ref().unwrap_or(&entry.path),
+        entry.name.clone(),
+        entry.playback_policy.clone(),
+        &entry.options,
+    );
+
+    let recorded = update(|entries| {
+        if let Some(e) = entries.iter_mut().find(|e| &e.id == id) {
+            match &result {
+                Ok(r) => {
+                    e.status = QueueStatus::Completed;
+                    e.asset_id = Some(r.asset_id.clone());
+                    e.task_id = Some(r.task_id.clone()).filter(|t| !t.is_empty());
+                    e.playback_id = Some(r.playback_id.clone());
+                    e.error = if r.duplicate {
+                        Some(String::from("Duplicate of an existing asset, skipped"))
+                    } else {
+                        None
+                    };
+                }
+                Err(err) => {
+                    e.status = QueueStatus::Failed;
+                    e.error = Some(err.clone());
+                }
+            }
+            e.pid = None;
+            e.pid_started = None;
+            e.updated_at = chrono::Utc::now().timestamp_millis();
+        }
+    });
+    if let Err(e) = recorded {
+        error!(
+            "The outcome of upload {} isn't recorded in the queue: {}",
+            id, e
+        );
+    }
+    if result.is_ok() && entry.temporary {
+        remove_temporary(&entry);
+    }
+    match result {
+        Ok(r) => Some(r),
+        Err(e) => {
+            error!("Upload of {} failed: {}", entry.path, e);
+            None
+        }
+    }
+}
+
+// Queue and immediately upload a single file
+pub fn upload(
+    client: &livepeer_rs::Livepeer,
+    path: &String,
+    name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: UploadOptions,
+) -> Option<UploadAssetResult> {
+    match enqueue(path, name, playback_policy, options) {
+        Ok(id) => run_entry(client, &id),
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+}
+
+// Queue and immediately upload the converted copy of a file when there is one. A copy whose
+// upload fails is kept for retries and removed once its entry completes or is purged.
+pub fn upload_converted(
+    client: &livepeer_rs::Livepeer,
+    path: &String,
+    converted: Option<super::convert::ConvertedFile>,
+    name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: UploadOptions,
+) -> Option<UploadAssetResult> {
+    let queued = match &converted {
+        Some(c) => enqueue_entry(&c.path, name, playback_policy, options, Some(path)),
+        None => enqueue(path, name, playback_policy, options),
+    };
+    let result = match queued {
+        Ok(id) => run_entry(client, &id),
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    };
+    if let (Some(c), None) = (&converted, &result) {
+        warn!(
+            "Keeping {} so the upload can be retried from the queue",
+            c.path
+        );
+    }
+    result
+}
+
+fn remove_temporary(entry: &QueueEntry) {
+    super::convert::ConvertedFile {
+        path: entry.path.clone(),
+    }
+    .remove();
+}
+
+pub fn pending() -> Result<Vec<QueueEntry>, String> {
+    Ok(list()?
+        .into_iter()
+        .filter(|e| e.status == QueueStatus::Pending)
+        .collect())
+}
+
+// Put a failed or interrupted entry back in the queue
+pub fn retry(id: &String) -> Result<(), String> {
+    update(|entries| {
+        recover_interrupted(entries);
+        let entry = entries
+            .iter_mut()
+            .find(|e| &e.id == id)
+            .ok_or(format!("No upload {} in the queue", id))?;
+        match entry.status {
+            QueueStatus::Failed | QueueStatus::Cancelled => {
+                entry.status = QueueStatus::Pending;
+                entry.error = None;
+                entry.updated_at = chrono::Utc::now().timestamp_millis();
+                Ok(())
+            }
+            s => Err(format!(
+                "Upload {} is {:?}, only failed or cancelled uploads can be retried",
+                id, s
+            )),
+        }
+    })?
+}
+
+pub fn cancel(id: &String) -> Result<(), String> {
+    update(|entries| {
+        recover_interrupted(entries);
+        let entry = entries
+            .iter_mut()
+            .find(|e| &e.id == id)
+            .ok_or(format!("No upload {} in the queue", id))?;
+        match entry.status {
+            QueueStatus::Pending | QueueStatus::Failed => {
+                entry.status = QueueStatus::Cancelled;
+                entry.updated_at = chrono::Utc::now().timestamp_millis();
+                Ok(())
+            }
+            s => Err(format!(
+                "Upload {} is {:?}, only pending or failed uploads can be cancelled",
+                id, s
+            )),
+        }
+    })?
+}
+
+// Remove completed and cancelled entries, and failed ones too when `failed` is set
+pub fn purge(failed: bool) -> Result<usize, String> {
+    update(|entries| {
+        recover_interrupted(entries);
+        let before = entries.len();
+        entries.retain(|e| {
+            let keep = match e.status {
+                QueueStatus::Completed | QueueStatus::Cancelled => false,
+                QueueStatus::Failed => !failed,
+                _ => true,
+            };
+            // Completed ones are removed after their upload, unless that failed
+            if !keep && e.temporary && std::path::Path::new(&e.path).exists() {
+                remove_temporary(e);
+            }
+            keep
+        });
+        before - entries.len()
+    })
+}
+
+pub fn to_value(entries: &Vec<QueueEntry>) -> serde_json::Value {
+    serde_json::to_value(entries).unwrap()
+}
+
+pub fn queue(client: &livepeer_rs::Livepeer) {
+    let entries = match list() {
+        Ok(e) => e,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+    if entries.is_empty() {
+        println!("The upload queue is empty");
+    } else {
+        println!(
+            "{}",
+            crate::output::render(
+                &to_value(&entries),
+                crate::output::Resource::Queue,
+                crate::output::OutputFormat::Table
+            )
+        );
+    }
+
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&[
+            "Run pending uploads",
+            "Retry an upload",
+            "Cancel an upload",
+            "Purge completed and cancelled",
+            "Purge completed, cancelled and failed",
+            "< Back",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(0) => match pending() {
+            Ok(entries) => {
+                for entry in entries {
+                    run_entry(client, &entry.id);
+                }
+            }
+            Err(e) => error!("{}", e),
+        },
+        Some(1) | Some(2) => {
+            let id = dialoguer::Input::<String>::new()
+                .with_prompt("Enter upload ID")
+                .interact()
+                .unwrap();
+            let result = if selection == Some(1) {
+                retry(&id).map(|_| {
+                    run_entry(client, &id);
+                })
+            } else {
+                cancel(&id)
+            };
+            if let Err(e) = result {
+                error!("{}", e);
+            }
+        }
+        Some(3) | Some(4) => match purge(selection == Some(4)) {
+            Ok(purged) => println!("Purged {} uploads", purged),
+            Err(e) => error!("{}", e),
+        },
+        Some(_) => return,
+        None => error!("No selection made"),
+    }
+    queue(client);
+}
@@ -0,0 +1,545 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::retry::Error;
+
+const TUS_VERSION: &str = "1.0.0";
+// Statuses of a tus upload that expired or was removed, it can't be resumed
+const EXPIRED_STATUSES: &[u16] = &[404, 410];
+pub const CHUNK_SIZE: usize = 8 * 1024 * 1024;
+// Sessions are stored in $HOME/.studio/resumable, one file per local file being uploaded
+const SESSIONS_DIR: &str = "resumable";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResumableSession {
+    pub path: String,
+    pub size: u64,
+    pub upload_url: String,
+    pub asset_id: String,
+    pub task_id: String,
+    pub playback_id: String,
+    pub offset: u64,
+}
+
+// Key a session by path, size and modification time so an edited file starts over
+fn session_filename(path: &String) -> Option<String> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let key = format!("{}:{}:{}", path, metadata.len(), modified);
+    Some(format!("{:x}", Sha256::digest(key.as_bytes())))
+}
+
+pub fn load_session(path: &String) -> Option<ResumableSession> {
+    let filename = session_filename(path)?;
+    let contents = crate::auth::get_string_from_disk(&String::from(SESSIONS_DIR), &filename)?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_session(session: &ResumableSession) {
+    if let Some(filename) = session_filename(&session.path) {
+        crate::auth::save_string_to_disk(
+            &String::from(SESSIONS_DIR),
+            &filename,
+            &serde_json::to_string(session).unwrap(),
+        );
+    }
+}
+
+pub fn remove_session(path: &String) {
+    if let Some(filename) = session_filename(path) {
+        crate::auth::remove_string_from_disk(&String::from(SESSIONS_DIR), &filename);
+    }
+}
+
+// Create a tus upload on the endpoint returned with the presigned urls and return its url,
+// without a length the upload is created with Upload-Defer-Length and sized by the last chunk
+pub fn create_upload(
+    tus_endpoint: &str,
+    file_name: &String,
+    length: Option<u64>,
+) -> Result<String, Error> {
+    let endpoint = super::parse_url(tus_endpoint, "tus endpoint")?;
+    let metadata = format!(
+        "filename {},filetype {}",
+        base64::encode(file_name),
+        base64::encode(super::content_type(file_name))
+    );
+
+    let response = async_std::task::block_on(async {
+        let request = surf::RequestBuilder::new(surf::http::Method::Post, endpoint.clone())
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Metadata", metadata);
+        match length {
+            Some(l) => request.header("Upload-Length", l.to_string()),
+            None => request.header("Upload-Defer-Length", "1"),
+        }
+        .await
+    })
+    .map_err(|e| Error::from(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(Error::http(
+            response.status() as u16,
+            format!("Error creating tus upload: {}", response.status()),
+        ));
+    }
+
+    let location = response
+        .header("Location")
+        .map(|l| l.last().as_str().to_string())
+        .ok_or(String::from("No Location returned for tus upload"))?;
+
+    // Location may be relative to the tus endpoint
+    endpoint
+        .join(&location)
+        .map(|u| u.to_string())
+        .map_err(|e| Error::fatal(format!("Invalid tus upload Location {:?}: {}", location, e)))
+}
+
+// Create a tus upload for the file and save it as a session
+pub fn create_session(
+    tus_endpoint: &str,
+    path: &String,
+    asset_id: String,
+    task_id: String,
+    playback_id: String,
+) -> Result<ResumableSession, Error> {
+    let size = std::fs::metadata(path)?.len();
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let session = ResumableSession {
+        path: path.clone(),
+        size,
+        upload_url: create_upload(tus_endpoint, &file_name, Some(size))?,
+        asset_id,
+        task_id,
+        playback_id,
+        offset: 0,
+    };
+    save_session(&session);
+    Ok(session)
+}
+
+// Send bytes at `offset`, setting the upload length with the last chunk of a deferred upload,
+// and return the offset the server reached
+pub fn patch_chunk(
+    upload_url: &str,
+    offset: u64,
+    chunk: &[u8],
+    pb: &indicatif::ProgressBar,
+    upload_length: Option<u64>,
+) -> Result<u64, Error> {
+    let url = super::parse_url(upload_url, "tus upload url")?;
+    let body = async_std::io::BufReader::new(super::progress::ProgressReader::new(
+        async_std::io::Cursor::new(chunk.to_vec()),
+        pb.clone(),
+    ));
+    let response = async_std::task::block_on(async {
+        let request = surf::RequestBuilder::new(surf::http::Method::Patch, url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Upload-Offset", offset.to_string())
+            .content_type("application/offset+octet-stream");
+        match upload_length {
+            Some(l) => request.header("Upload-Length", l.to_string()),
+            None => request,
+        }
+        .body(surf::Body::from_reader(body, Some(chunk.len())))
+        .await
+    })
+    .map_err(|e| Error::from(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(Error::http(
+            response.status() as u16,
+            format!("Error uploading chunk: {}", response.status()),
+        ));
+    }
+
+    Ok(response
+        .header("Upload-Offset")
+        .and_then(|o| o.last().as_str().parse::<u64>().ok())
+        .unwrap_or(offset + chunk.len() as u64))
+}
+
+pub fn get_offset(upload_url: &str) -> Result<u64, Error> {
+    let url = super::parse_url(upload_url, "tus upload url")?;
+    let response = async_std::task::block_on(async {
+        surf::RequestBuilder::new(surf::http::Method::Head, url)
+            .header("Tus-Resumable", TUS_VERSION)
+            .await
+    })
+    .map_err(|e| Error::from(e.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(Error::http(
+            response.status() as u16,
+            format!("Error getting tus upload offset: {}", response.status()),
+        ));
+    }
+
+    response
+        .header("Upload-Offset")
+        .and_then(|o| o.last().as_str().parse::<u64>().ok())
+        .ok_or(Error::from(String::from(
+            "No Upload-Offset returned for tus upload",
+        )))
+}
+
+pub fn is_expired(error: &Error) -> bool {
+    error
+        .status
+        .map_or(false, |s| EXPIRED_STATUSES.contains(&s))
+}
+
+// Whether the server no longer has the upload of a saved session, other errors are left to
+// the upload itself
+pub fn session_expired(session: &ResumableSession) -> bool {
+    matches!(get_offset(&session.upload_url), Err(e) if is_expired(&e))
+}
+
+// Fill the chunk unless the reader ends first, returns the number of bytes read
+pub fn read_chunk(reader: &mut impl std::io::Read, chunk: &mut [u8]) -> Result<usize, Error> {
+    let mut read = 0;
+    while read < chunk.len() {
+        let n = reader.read(&mut chunk[read..])?;
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    Ok(read)
+}
+
+// Upload the remaining bytes of a session, saving the offset after every chunk. A session whose
+// upload expired on the server is dropped.
+pub fn upload(session: &mut ResumableSession) -> Result<(), Error> {
+    match upload_chunks(session) {
+        Err(e) if is_expired(&e) => {
+            // Nothing left to resume, the next upload of the file starts over
+            remove_session(&session.path);
+            Err(Error {
+                message: format!("{}, the tus upload expired", e.message),
+                ..e
+            })
+        }
+        result => result,
+    }
+}
+
+fn upload_chunks(session: &mut ResumableSession) -> Result<(), Error> {
+    use std::io::{Seek, SeekFrom};
+
+    // The server is the source of truth, the local offset may be behind after a crash
+    session.offset = get_offset(&session.upload_url)?;
+    save_session(session);
+
+    let mut file = std::fs::File::open(&session.path)?;
+
+    let pb = super::progress::new_bytes_bar(session.size, &session.path);
+    pb.set_position(session.offset);
+
+    let mut chunk = vec![0; CHUNK_SIZE];
+    while session.offset < session.size {
+        file.seek(SeekFrom::Start(session.offset))?;
+        let read = read_chunk(&mut file, &mut chunk)?;
+        if read == 0 {
+            return Err(Error::fatal(format!(
+                "File {} is shorter than expected ({} of {} bytes)",
+                session.path, session.offset, session.size
+            )));
+        }
+
+        session.offset = match patch_chunk(
+            &session.upload_url,
+            session.offset,
+            &chunk[..read],
+            &pb,
+            None,
+        ) {
+            Ok(o) => o,
+            // The server has a different offset, e.g. a failed request stored part of a chunk,
+            // continue from the offset it has
+            Err(e) if e.status == Some(409) => match get_offset(&session.upload_url) {
+                Ok(o) if o != session.offset => {
+                    warn!("Upload offset mismatch, resuming from byte {}", o);
+                    o
+                }
+                Ok(_) => {
+                    pb.abandon();
+                    return Err(e);
+                }
+                Err(head_error) => {
+                    pb.abandon();
+                    return Err(head_error);
+                }
+            },
+            Err(e) => {
+                pb.abandon();
+                return Err(e);
+            }
+        };
+        save_session(session);
+        pb.set_position(session.offset);
+    }
+
+    pb.finish();
+    remove_session(&session.path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::sync::{Arc, Mutex};
+
+    // In-memory tus server, one upload at /files/1
+    #[derive(Default)]
+    struct Tus {
+        length: Option<u64>,
+        data: Vec<u8>,
+        // Upload-Offset of every PATCH received
+        patches: Vec<u64>,
+        // Fail the PATCH with this index with a 500 without storing it
+        fail_patch: Option<usize>,
+        // Bytes stored behind the client's back before the first PATCH, which then gets a 409
+        stored_elsewhere: Option<Vec<u8>>,
+        // Upload-Metadata of the POST
+        metadata: Option<String>,
+        // Answer 404 to HEAD and PATCH as for an upload removed by the server
+        expired: bool,
+    }
+
+    fn read_request(
+        stream: &mut std::net::TcpStream,
+    ) -> Option<(String, std::collections::HashMap<String, String>, Vec<u8>)> {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let method = line.split(' ').next()?.to_string();
+        let mut headers = std::collections::HashMap::new();
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).ok()?;
+            if header.trim().is_empty() {
+                break;
+            }
+            let (name, value) = header.split_once(':')?;
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+        if headers.get("expect").map_or(false, |e| e == "100-continue") {
+            stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").ok()?;
+        }
+        let mut body = vec![];
+        if let Some(length) = headers.get("content-length") {
+            body = vec![0; length.parse().ok()?];
+            reader.read_exact(&mut body).ok()?;
+        } else if headers
+            .get("transfer-encoding")
+            .map_or(false, |t| t == "chunked")
+        {
+            loop {
+                let mut size = String::new();
+                reader.read_line(&mut size).ok()?;
+                let size = usize::from_str_radix(size.trim(), 16).ok()?;
+                let mut part = vec![0; size + 2];
+                reader.read_exact(&mut part).ok()?;
+                if size == 0 {
+                    break;
+                }
+                body.extend_from_slice(&part[..size]);
+            }
+        }
+        Some((method, headers, body))
+    }
+
+    fn respond(
+        tus: &Arc<Mutex<Tus>>,
+        method: &str,
+        headers: &std::collections::HashMap<String, String>,
+        body: Vec<u8>,
+    ) -> String {
+        let mut tus = tus.lock().unwrap();
+        if tus.expired && method != "POST" {
+            return String::from("HTTP/1.1 404 Not Found\r\n");
+        }
+        match method {
+            "POST" => {
+                tus.length = headers.get("upload-length").and_then(|l| l.parse().ok());
+                tus.metadata = headers.get("upload-metadata").cloned();
+                String::from("HTTP/1.1 201 Created\r\nLocation: /files/1\r\n")
+            }
+            "HEAD" => format!("HTTP/1.1 200 OK\r\nUpload-Offset: {}\r\n", tus.data.len()),
+            "PATCH" => {
+                let offset = headers["upload-offset"].parse::<u64>().unwrap();
+                tus.patches.push(offset);
+                if tus.fail_patch == Some(tus.patches.len() - 1) {
+                    return String::from("HTTP/1.1 500 Internal Server Error\r\n");
+                }
+                if let Some(stored) = tus.stored_elsewhere.take() {
+                    tus.data = stored;
+                }
+                if offset != tus.data.len() as u64 {
+                    return String::from("HTTP/1.1 409 Conflict\r\n");
+                }
+                tus.data.extend_from_slice(&body);
+                format!(
+                    "HTTP/1.1 204 No Content\r\nUpload-Offset: {}\r\n",
+                    tus.data.len()
+                )
+            }
+            _ => String::from("HTTP/1.1 405 Method Not Allowed\r\n"),
+        }
+    }
+
+    // Serve the tus upload on a local port, returns the tus endpoint
+    fn serve(tus: Arc<Mutex<Tus>>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/files/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                if let Some((method, headers, body)) = read_request(&mut stream) {
+                    let status = respond(&tus, &method, &headers, body);
+                    let _ = stream.write_all(
+                        format!("{}Tus-Resumable: 1.0.0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status)
+                            .as_bytes(),
+                    );
+                }
+            }
+        });
+        endpoint
+    }
+
+    // A file of `size` bytes in a temporary home, sessions are saved under it
+    fn test_file(name: &str, size: usize) -> (String, Vec<u8>) {
+        let home = std::env::temp_dir().join("studio-resumable-tests");
+        std::fs::create_dir_all(home.join(".studio")).unwrap();
+        std::env::set_var("HOME", &home);
+        let contents = (0..size).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+        let path = home.join(name);
+        std::fs::write(&path, &contents).unwrap();
+        (path.to_str().unwrap().to_string(), contents)
+    }
+
+    fn new_session(tus: &Arc<Mutex<Tus>>, path: &String) -> ResumableSession {
+        create_session(
+            &serve(tus.clone()),
+            path,
+            String::from("asset"),
+            String::from("task"),
+            String::from("playback"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn creates_upload_and_saves_session() {
+        let (path, contents) = test_file("create.mp4", 1000);
+        let tus = Arc::new(Mutex::new(Tus::default()));
+        let session = new_session(&tus, &path);
+
+        assert!(session.upload_url.ends_with("/files/1"));
+        assert_eq!(tus.lock().unwrap().length, Some(contents.len() as u64));
+        assert_eq!(load_session(&path).unwrap().upload_url, session.upload_url);
+    }
+
+    #[test]
+    fn uploads_in_chunks_and_removes_session() {
+        let (path, contents) = test_file("chunks.mp4", CHUNK_SIZE + 1000);
+        let tus = Arc::new(Mutex::new(Tus::default()));
+        let mut session = new_session(&tus, &path);
+
+        upload(&mut session).unwrap();
+
+        let tus = tus.lock().unwrap();
+        assert_eq!(tus.patches, vec![0, CHUNK_SIZE as u64]);
+        assert!(tus.data == contents);
+        assert!(load_session(&path).is_none());
+    }
+
+    #[test]
+    fn resumes_from_saved_offset() {
+        let (path, contents) = test_file("resume.mp4", CHUNK_SIZE * 2 + 1000);
+        let tus = Arc::new(Mutex::new(Tus {
+            fail_patch: Some(1),
+            ..Default::default()
+        }));
+        let mut session = new_session(&tus, &path);
+
+        let error = upload(&mut session).unwrap_err();
+        assert_eq!(error.status, Some(500));
+        let mut saved = load_session(&path).unwrap();
+        assert_eq!(saved.offset, CHUNK_SIZE as u64);
+
+        upload(&mut saved).unwrap();
+
+        let tus = tus.lock().unwrap();
+        assert_eq!(
+            tus.patches,
+            vec![
+                0,
+                CHUNK_SIZE as u64,
+                CHUNK_SIZE as u64,
+                CHUNK_SIZE as u64 * 2
+            ]
+        );
+        assert!(tus.data == contents);
+        assert!(load_session(&path).is_none());
+    }
+
+    #[test]
+    fn continues_from_server_offset_on_conflict() {
+        let (path, contents) = test_file("conflict.mp4", 5000);
+        let tus = Arc::new(Mutex::new(Tus {
+            stored_elsewhere: Some(contents[..2000].to_vec()),
+            ..Default::default()
+        }));
+        let mut session = new_session(&tus, &path);
+
+        upload(&mut session).unwrap();
+
+        let tus = tus.lock().unwrap();
+        assert_eq!(tus.patches, vec![0, 2000]);
+        assert!(tus.data == contents);
+    }
+
+    #[test]
+    fn sends_file_type_from_extension() {
+        let (path, _) = test_file("type.mov", 1000);
+        let tus = Arc::new(Mutex::new(Tus::default()));
+        new_session(&tus, &path);
+
+        let metadata = tus.lock().unwrap().metadata.clone().unwrap();
+        assert!(metadata.contains(&format!("filetype {}", base64::encode("video/quicktime"))));
+    }
+
+    #[test]
+    fn rejects_invalid_endpoint() {
+        let error = create_upload("", &String::from("empty.mp4"), Some(1)).unwrap_err();
+        assert_eq!(error.kind, crate::assets::upload::retry::ErrorKind::Fatal);
+    }
+
+    #[test]
+    fn drops_expired_session() {
+        let (path, _) = test_file("expired.mp4", 1000);
+        let tus = Arc::new(Mutex::new(Tus::default()));
+        let mut session = new_session(&tus, &path);
+        tus.lock().unwrap().expired = true;
+
+        assert!(session_expired(&session));
+        let error = upload(&mut session).unwrap_err();
+        assert_eq!(error.status, Some(404));
+        assert!(load_session(&path).is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// HTTP statuses worth retrying, every other 4xx means the request itself is wrong
+const RETRIABLE_STATUSES: &'static [u16] = &[408, 425, 429];
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    // Total number of tries, 1 disables retries
+    pub attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // Fraction of the backoff added or removed at random so parallel uploads don't retry together
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 4,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30000,
+            jitter: 0.25,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorKind {
+    Retriable,
+    Fatal,
+}
+
+// Error of one try, with the HTTP status of the response when there was one
+#[derive(Clone, Debug)]
+pub struct Error {
+    pub status: Option<u16>,
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl Error {
+    pub fn http(status: u16, message: String) -> Self {
+        Error {
+            status: Some(status),
+            kind: classify(status),
+            message,
+        }
+    }
+
+    // Local errors trying again can't fix, e.g. a file that doesn't exist
+    pub fn fatal(message: String) -> Self {
+        Error {
+            status: None,
+            kind: ErrorKind::Fatal,
+            message,
+        }
+    }
+}
+
+// Network failures, which have no status, are retried
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error {
+            status: None,
+            kind: ErrorKind::Retriable,
+            message,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound
+            | std::io::ErrorKind::PermissionDenied
+            | std::io::ErrorKind::InvalidInput => Error::fatal(e.to_string()),
+            _ => Error::from(e.to_string()),
+        }
+    }
+}
+
+impl From<Error> for String {
+    fn from(e: Error) -> Self {
+        e.message
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Client errors are fatal except timeouts and rate limits, server errors are retried
+pub fn classify(status: u16) -> ErrorKind {
+    if (400..500).contains(&status) && !RETRIABLE_STATUSES.contains(&status) {
+        ErrorKind::Fatal
+    } else {
+        ErrorKind::Retriable
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(attempt - 1))
+            .min(self.max_backoff_ms) as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + rand::thread_rng().gen_range(-jitter..=jitter);
+        std::time::Duration::from_millis((backoff * factor) as u64)
+    }
+
+    // Run `f` until it succeeds, fails with a fatal error or runs out of attempts
+    pub fn run<T, E: Into<Error>>(
+        &self,
+        what: &str,
+        mut f: impl FnMut() -> Result<T, E>,
+    ) -> Result<T, String> {
+        let attempts = self.attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            match f().map_err(Into::into) {
+                Ok(r) => return Ok(r),
+                Err(e) => {
+                    if e.kind == ErrorKind::Fatal {
+                        return Err(format!("{} failed: {}", what, e));
+                    }
+                    if attempt >= attempts {
+                        return Err(format!("{} failed after {} attempts: {}", what, attempts, e));
+                    }
+                    let backoff = self.backoff(attempt);
+                    warn!(
+                        "{} failed ({}), attempt {}/{}, retrying in {:.1}s",
+                        what,
+                        e,
+                        attempt,
+                        attempts,
+                        backoff.as_secs_f64()
+                    );
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
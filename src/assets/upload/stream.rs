@@ -0,0 +1,169 @@
+use super::{resumable, UploadAssetResult, UploadOptions};
+
+// Path meaning the upload is read from stdin
+pub const STDIN: &str = "-";
+
+// stdin or a named pipe, which can only be read once and have no known size
+pub fn is_stream(path: &String) -> bool {
+    if path == STDIN {
+        return true;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        return std::fs::metadata(path).map_or(false, |m| m.file_type().is_fifo());
+    }
+
+    #[cfg(not(unix))]
+    {
+        return false;
+    }
+}
+
+fn open(path: &String) -> Result<Box<dyn std::io::Read + Send>, String> {
+    if path == STDIN {
+        Ok(Box::new(std::io::stdin()))
+    } else {
+        Ok(Box::new(
+            std::fs::File::open(path).map_err(|e| e.to_string())?,
+        ))
+    }
+}
+
+// Upload stdin or a named pipe as it is read, without a temporary file. The bytes can't be
+// read again, so only the presigned url and single tus chunks (kept in memory) are retried.
+pub fn upload_stream(
+    path: &String,
+    asset_name: String,
+    playback_policy: Option<serde_json::Value>,
+    options: &UploadOptions,
+) -> Result<UploadAssetResult, String> {
+    info!("Generating presigned urls");
+
+    let urls = options.retry.run("Generating presigned urls", || {
+        super::request_upload(&asset_name, &playback_policy)
+    })?;
+
+    let asset_id = urls["asset"]["id"].as_str().unwrap().to_string();
+    let task_id = urls["task"]["id"].as_str().unwrap().to_string();
+    let playback_id = urls["asset"]["playbackId"].as_str().unwrap().to_string();
+
+    let pb = super::progress::new_stream_bar(&asset_name);
+    pb.enable_steady_tick(std::time::Duration::from_millis(120));
+    let result = if options.resumable {
+        tus_stream(
+            urls["tusEndpoint"].as_str().unwrap_or(""),
+            path,
+            &asset_name,
+            &pb,
+            options,
+        )
+    } else {
+        put_stream(urls["url"].as_str().unwrap_or(""), path, &pb)
+    };
+    match &result {
+        Ok(_) => pb.finish(),
+        Err(_) => pb.abandon(),
+    }
+    result?;
+
+    info!("Upload successful");
+    Ok(UploadAssetResult {
+        asset_id,
+        task_id,
+        playback_id,
+        duplicate: false,
+    })
+}
+
+// PUT with a chunked body, as the length is unknown
+fn put_stream(upload_url: &str, path: &String, pb: &indicatif::ProgressBar) -> Result<(), String> {
+    let url = super::parse_url(upload_url, "upload url")?;
+    let response = async_std::task::block_on(async {
+        let reader: Box<dyn async_std::io::Read + Unpin + Send + Sync> = if path == STDIN {
+            Box::new(async_std::io::stdin())
+        } else {
+            Box::new(async_std::fs::File::open(path).await?)
+        };
+        let reader =
+            async_std::io::BufReader::new(super::progress::ProgressReader::new(reader, pb.clone()));
+        surf::RequestBuilder::new(surf::http::Method::Put, url)
+            .body(surf::Body::from_reader(reader, None))
+            .await
+    })
+    .map_err(|e| e.to_string())?;
+
+    if !response.status().is_success() {
+        return Err(format!("Error uploading file: {}", response.status()));
+    }
+    Ok(())
+}
+
+// Deferred length tus upload. The next chunk is read before sending the current one so the
+// last chunk is known and can carry the final length.
+fn tus_stream(
+    tus_endpoint: &str,
+    path: &String,
+    asset_name: &String,
+    pb: &indicatif::ProgressBar,
+    options: &UploadOptions,
+) -> Result<(), String> {
+    let upload_url = options.retry.run("Creating tus upload", || {
+        resumable::create_upload(tus_endpoint, asset_name, None)
+    })?;
+
+    let mut reader = open(path)?;
+    let mut current = vec![0; resumable::CHUNK_SIZE];
+    let mut next = vec![0; resumable::CHUNK_SIZE];
+    let mut current_len = resumable::read_chunk(&mut reader, &mut current)?;
+    if current_len == 0 {
+        return Err(String::from("Nothing to upload, the input is empty"));
+    }
+    let mut offset = 0;
+
+    loop {
+        let next_len = if current_len < resumable::CHUNK_SIZE {
+            0
+        } else {
+            resumable::read_chunk(&mut reader, &mut next)?
+        };
+        let upload_length = if next_len == 0 {
+            Some(offset + current_len as u64)
+        } else {
+            None
+        };
+
+        let chunk_start = offset;
+        let chunk = &current[..current_len];
+        let mut retrying = false;
+        offset = options.retry.run("Uploading chunk", || {
+            // A failed request may have stored part of the chunk, continue from the server offset
+            let sent = if retrying {
+                resumable::get_offset(&upload_url)?.saturating_sub(chunk_start) as usize
+            } else {
+                0
+            };
+            retrying = true;
+            pb.set_position(chunk_start + sent as u64);
+            resumable::patch_chunk(
+                &upload_url,
+                chunk_start + sent as u64,
+                &chunk[sent.min(chunk.len())..],
+                pb,
+                upload_length,
+            )
+            .map_err(|e| match e.status {
+                // Offset mismatch, retried from the server offset
+                Some(409) => super::retry::Error::from(e.message),
+                _ => e,
+            })
+        })?;
+
+        if upload_length.is_some() {
+            return Ok(());
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+    }
+}
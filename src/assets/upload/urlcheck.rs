@@ -0,0 +1,252 @@
+use colored::*;
+use serde::Serialize;
+
+use super::probe::PreflightAction;
+
+const MAX_REDIRECTS: usize = 10;
+// Signed urls expiring sooner than this may expire before the import task downloads them
+const MIN_EXPIRY_SECS: i64 = 3600;
+// Content types Studio can import besides video/* and audio/*
+const IMPORTABLE_TYPES: &'static [&'static str] = &[
+    "application/octet-stream",
+    "binary/octet-stream",
+    "application/mp4",
+    "application/x-mpegurl",
+    "application/vnd.apple.mpegurl",
+    "application/x-matroska",
+];
+
+#[derive(Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UrlReport {
+    pub url: String,
+    pub final_url: String,
+    pub redirects: Vec<String>,
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub expires_at: Option<String>,
+    pub warnings: Vec<String>,
+    // Problems that will make the import fail, as opposed to warnings
+    pub errors: Vec<String>,
+}
+
+// Follow redirects by hand to report every hop. A GET of the first byte is used rather than
+// HEAD, which urls signed for GET only and some servers reject.
+fn fetch(report: &mut UrlReport) -> Result<surf::Response, String> {
+    let mut url = report.url.clone();
+    loop {
+        report.final_url = url.clone();
+        let response = async_std::task::block_on(async {
+            surf::get(&url).header("Range", "bytes=0-0").await
+        })
+        .map_err(|e| e.to_string())?;
+        if !response.status().is_redirection() {
+            return Ok(response);
+        }
+        if report.redirects.len() >= MAX_REDIRECTS {
+            return Err(format!("More than {} redirects", MAX_REDIRECTS));
+        }
+        let location = response
+            .header("Location")
+            .map(|l| l.last().as_str().to_string())
+            .ok_or(format!("Redirect {} without a Location", response.status()))?;
+        report.redirects.push(url.clone());
+        url = surf::Url::parse(&url)
+            .and_then(|u| u.join(&location))
+            .map_err(|e| e.to_string())?
+            .to_string();
+    }
+}
+
+// Expiry of urls signed by S3, GCS, CloudFront or Azure
+fn signed_url_expiry(url: &surf::Url) -> Option<chrono::DateTime<chrono::Utc>> {
+    let query = url
+        .query_pairs()
+        .map(|(k, v)| (k.to_lowercase(), v.to_string()))
+        .collect::<std::collections::HashMap<String, String>>();
+
+    let signed_at = |date: &str| {
+        chrono::NaiveDateTime::parse_from_str(date, "%Y%m%dT%H%M%SZ")
+            .ok()
+            .map(|d| d.and_utc())
+    };
+    if let (Some(date), Some(expires)) = (query.get("x-amz-date"), query.get("x-amz-expires")) {
+        return Some(signed_at(date)? + chrono::Duration::seconds(expires.parse().ok()?));
+    }
+    if let (Some(date), Some(expires)) = (query.get("x-goog-date"), query.get("x-goog-expires")) {
+        return Some(signed_at(date)? + chrono::Duration::seconds(expires.parse().ok()?));
+    }
+    if let Some(expires) = query.get("expires") {
+        return chrono::DateTime::from_timestamp(expires.parse().ok()?, 0);
+    }
+    if let Some(expires) = query.get("se") {
+        return chrono::DateTime::parse_from_rfc3339(expires)
+            .ok()
+            .map(|d| d.with_timezone(&chrono::Utc));
+    }
+    None
+}
+
+pub fn check_url(url: &String) -> UrlReport {
+    let mut report = UrlReport {
+        url: url.clone(),
+        final_url: url.clone(),
+        ..Default::default()
+    };
+
+    let parsed = match surf::Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => {
+            report.errors.push(format!("Invalid url: {}", e));
+            return report;
+        }
+    };
+    // ipfs:// and ar:// urls are resolved by Studio
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        report
+            .warnings
+            .push(format!("{} urls can't be checked", parsed.scheme()));
+        return report;
+    }
+
+    if let Some(expiry) = signed_url_expiry(&parsed) {
+        report.expires_at = Some(expiry.to_rfc3339());
+        let left = (expiry - chrono::Utc::now()).num_seconds();
+        if left <= 0 {
+            report
+                .errors
+                .push(format!("Signed url expired at {}", expiry));
+        } else if left < MIN_EXPIRY_SECS {
+            report.warnings.push(format!(
+                "Signed url expires in {} minutes, the import may start after that",
+                left / 60
+            ));
+        }
+    }
+
+    let response = match fetch(&mut report) {
+        Ok(r) => r,
+        Err(e) => {
+            report.errors.push(format!("Unreachable: {}", e));
+            return report;
+        }
+    };
+
+    let status = response.status();
+    report.status = Some(status as u16);
+    report.content_type = response
+        .header("Content-Type")
+        .map(|c| c.last().as_str().to_string());
+    // A ranged GET returns the full size after the slash of Content-Range
+    report.content_length = response
+        .header("Content-Range")
+        .and_then(|r| r.last().as_str().rsplit('/').next()?.parse().ok())
+        .or_else(|| {
+            response
+                .header("Content-Length")
+                .and_then(|l| l.last().as_str().parse().ok())
+        });
+
+    match status as u16 {
+        200..=299 => {}
+        401 | 403 => report.errors.push(format!("Access denied ({})", status)),
+        404 | 410 => report.errors.push(format!("Not found ({})", status)),
+        s => report.errors.push(format!("Request failed ({})", s)),
+    }
+    if !report.errors.is_empty() {
+        return report;
+    }
+
+    let mime = report
+        .content_type
+        .as_ref()
+        .map(|c| c.split(';').next().unwrap().trim().to_lowercase());
+    match mime.as_deref() {
+        Some("text/html") => report.errors.push(String::from(
+            "Url points to a web page, not to a video file",
+        )),
+        Some(m)
+            if !m.starts_with("video/")
+                && !m.starts_with("audio/")
+                && !IMPORTABLE_TYPES.contains(&m) =>
+        {
+            report
+                .warnings
+                .push(format!("Unexpected content type {}", m))
+        }
+        None => report.warnings.push(String::from("No content type")),
+        _ => {}
+    }
+    match report.content_length {
+        Some(0) => report.errors.push(String::from("The file is empty")),
+        None => report.warnings.push(String::from("Unknown size")),
+        _ => {}
+    }
+
+    report
+}
+
+pub fn print_report(report: &UrlReport) {
+    let na = String::from("-");
+    eprintln!("{}", report.url.cyan().bold());
+    // The first entry of the chain is the url itself
+    if !report.redirects.is_empty() {
+        for hop in report
+            .redirects
+            .iter()
+            .skip(1)
+            .chain(std::iter::once(&report.final_url))
+        {
+            eprintln!("  Redirect:   {}", hop);
+        }
+    }
+    eprintln!(
+        "  Status:     {}",
+        report.status.map_or(na.clone(), |s| s.to_string())
+    );
+    eprintln!(
+        "  Type:       {}",
+        report.content_type.as_ref().unwrap_or(&na)
+    );
+    eprintln!(
+        "  Size:       {}",
+        report
+            .content_length
+            .map_or(na.clone(), |l| indicatif::HumanBytes(l).to_string())
+    );
+    if let Some(expires_at) = &report.expires_at {
+        eprintln!("  Expires:    {}", expires_at);
+    }
+    for warning in &report.warnings {
+        eprintln!("  {} {}", "⚠".yellow().bold(), warning.yellow());
+    }
+    for error in &report.errors {
+        eprintln!("  {} {}", "✗".red().bold(), error.red());
+    }
+}
+
+// Check a url before importing it, returns whether the import should go on
+pub fn preflight(url: &String, action: PreflightAction) -> bool {
+    if action == PreflightAction::Off {
+        return true;
+    }
+    let report = check_url(url);
+    print_report(&report);
+    if report.errors.is_empty() {
+        return true;
+    }
+
+    match action {
+        PreflightAction::Ask => dialoguer::Confirm::new()
+            .with_prompt("This import will probably fail. Import anyway?")
+            .default(false)
+            .interact()
+            .unwrap(),
+        PreflightAction::Reject => {
+            error!("Not importing {}: {}", url, report.errors.join(", "));
+            false
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+const DONE_FOLDER: &str = "done";
+const FAILED_FOLDER: &str = "failed";
+// Sidecar written next to a processed file when files are marked instead of moved
+const MARKER_EXTENSION: &str = "studio.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum DoneAction {
+    /// Move processed files into the done/ or failed/ subfolder
+    Move,
+    /// Leave processed files in place and write a <file>.studio.json marker next to them
+    Mark,
+}
+
+struct Candidate {
+    size: u64,
+    modified: std::time::SystemTime,
+    stable_polls: u32,
+}
+
+// Watch a folder forever, uploading every new video file once its size stops changing
+pub fn watch_folder(
+    client: &livepeer_rs::Livepeer,
+    folder: &String,
+    interval: std::time::Duration,
+    settle_polls: u32,
+    action: DoneAction,
+    playback_policy: Option<serde_json::Value>,
+) {
+    info!("Watching {} for new files", folder);
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+
+    loop {
+        let files = match super::batch::collect_files(folder, false) {
+            Ok(f) => f,
+            Err(e) => {
+                error!("Error listing {}: {}", folder, e);
+                vec![]
+            }
+        };
+
+        candidates.retain(|path, _| files.contains(path));
+
+        for path in files {
+            if marker_path(&path).exists() {
+                continue;
+            }
+            let metadata = match std::fs::metadata(&path) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let modified = metadata.modified().unwrap_or(std::time::UNIX_EPOCH);
+
+            let candidate = candidates.entry(path.clone()).or_insert(Candidate {
+                size: metadata.len(),
+                modified,
+                stable_polls: 0,
+            });
+            if candidate.size == metadata.len() && candidate.modified == modified {
+                candidate.stable_polls += 1;
+            } else {
+                debug!("{} is still being written", path);
+                candidate.size = metadata.len();
+                candidate.modified = modified;
+                candidate.stable_polls = 0;
+            }
+
+            if candidate.stable_polls >= settle_polls {
+                candidates.remove(&path);
+                process_file(client, &path, action, playback_policy.clone());
+            }
+        }
+
+        std::thread::sleep(interval);
+    }
+}
+
+fn process_file(
+    client: &livepeer_rs::Livepeer,
+    path: &String,
+    action: DoneAction,
+    playback_policy: Option<serde_json::Value>,
+) {
+    println!("New file {}", path);
+    let asset_name = std::path::Path::new(path)
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+
+    // Nobody is there to answer a prompt, duplicates are skipped and problems only reported
+    let options = super::UploadOptions {
+        on_duplicate: super::dedup::DuplicateAction::Skip,
+        preflight: super::probe::PreflightAction::Warn,
+        ..Default::default()
+    };
+    let result = super::queue::upload(client, path, asset_name, playback_policy, options);
+    let (completed, record) = match &result {
+        Some(r) if r.duplicate => (
+            true,
+            serde_json::json!({
+                "file": path,
+                "status": "duplicate",
+                "assetId": r.asset_id,
+                "playbackId": r.playback_id,
+            }),
+        ),
+        Some(r) => {
+            println!("Uploaded {} as asset {}, tracking task {}", path, r.asset_id, r.task_id);
+            let completed =
+                crate::tasks::track_task_status(serde_json::json!({ "id": r.task_id }), client);
+            (
+                completed,
+                serde_json::json!({
+                    "file": path,
+                    "status": if completed { "completed" } else { "failed" },
+                    "assetId": r.asset_id,
+                    "taskId": r.task_id,
+                    "playbackId": r.playback_id,
+                }),
+            )
+        }
+        None => (
+            false,
+            serde_json::json!({
+                "file": path,
+                "status": "failed",
+            }),
+        ),
+    };
+
+    if !completed {
+        error!("Processing of {} failed", path);
+    }
+
+    match action {
+        DoneAction::Move => {
+            let folder = std::path::Path::new(path).parent().unwrap().join(if completed {
+                DONE_FOLDER
+            } else {
+                FAILED_FOLDER
+            });
+            let target = folder.join(std::path::Path::new(path).file_name().unwrap());
+            let moved = std::fs::create_dir_all(&folder).and_then(|_| std::fs::rename(path, &target));
+            match moved {
+                Ok(_) => info!("Moved {} to {}", path, target.display()),
+                Err(e) => {
+                    // Fall back to a marker so the file is not uploaded again
+                    error!("Error moving {}: {}", path, e);
+                    write_marker(path, &record);
+                }
+            }
+        }
+        DoneAction::Mark => write_marker(path, &record),
+    }
+}
+
+fn marker_path(path: &String) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!("{}.{}", path, MARKER_EXTENSION))
+}
+
+fn write_marker(path: &String, record: &serde_json::Value) {
+    let marker = marker_path(path);
+    match std::fs::write(&marker, serde_json::to_string_pretty(record).unwrap()) {
+        Ok(_) => info!("Marked {} as processed", path),
+        Err(e) => error!("Error writing {}: {}", marker.display(), e),
+    }
+}
+
+pub fn watch(client: &livepeer_rs::Livepeer) {
+    let current_folder_string = std::env::current_dir()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let folder = dialoguer::Input::<String>::new()
+        .with_prompt("Enter the folder to watch")
+        .default(current_folder_string)
+        .interact()
+        .unwrap();
+    let action = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("When a file is processed")
+        .items(&[
+            "Move it into the done/ or failed/ subfolder",
+            "Leave it and write a .studio.json marker",
+        ])
+        .default(0)
+        .interact()
+        .unwrap();
+    let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
+
+    println!("Watching {}, press Ctrl-C to stop", folder);
+    watch_folder(
+        client,
+        &folder,
+        std::time::Duration::from_secs(5),
+        2,
+        if action == 0 { DoneAction::Move } else { DoneAction::Mark },
+        playback_policy,
+    );
+}
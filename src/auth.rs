@@ -5,6 +5,32 @@ use std::collections::HashMap;
 use std::io::*;
 use std::path::PathBuf;
 
+// Env of the client currently in use, for data stored per env
+static CURRENT_ENV: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+pub fn set_current_env(env: &str) {
+    *CURRENT_ENV.lock().unwrap() = Some(env.to_string());
+}
+
+pub fn current_env() -> String {
+    CURRENT_ENV
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or(String::from("prod"))
+}
+
+// Api key of the client currently in use, for the requests livepeer_rs doesn't cover
+static CURRENT_API_KEY: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+pub fn set_current_api_key(api_key: &str) {
+    *CURRENT_API_KEY.lock().unwrap() = Some(api_key.to_string());
+}
+
+pub fn current_api_key() -> Option<String> {
+    CURRENT_API_KEY.lock().unwrap().clone()
+}
+
 #[derive(Serialize, Deserialize)]
 struct ApiKeyInfo {
     email: String,
@@ -77,6 +103,37 @@ pub fn load_api_keys_from_disk(env: &String) -> Option<String> {
     }
 }
 
+// Load the only api key stored for $ENV, used when no menu can be shown
+pub fn load_default_api_key(env: &String) -> Option<String> {
+    let home = dirs::home_dir().unwrap();
+    let env_path = home.join(".studio").join(env);
+
+    let mut api_keys = vec![];
+    for entry in std::fs::read_dir(&env_path).ok()? {
+        let path = entry.ok()?.path();
+        if path.is_file() {
+            let contents = std::fs::read_to_string(&path).ok()?;
+            let api_key_info: ApiKeyInfo = serde_json::from_str(&contents).ok()?;
+            api_keys.push(api_key_info.api_key);
+        }
+    }
+
+    match api_keys.len() {
+        0 => {
+            error!("No Api Keys found {}", env_path.display());
+            None
+        }
+        1 => api_keys.pop(),
+        _ => {
+            error!(
+                "Multiple Api Keys found {}, pass one with --api-key",
+                env_path.display()
+            );
+            None
+        }
+    }
+}
+
 pub fn ask_create_api_key(env: &String, path: &PathBuf) -> String {
     let api_key = dialoguer::Input::new()
         .with_prompt(format!("Please enter your api key for the env {}", env))
@@ -156,3 +213,12 @@ pub fn get_string_from_disk(env: &String, filename: &String) -> Option<String> {
 
     Some(contents)
 }
+
+pub fn remove_string_from_disk(env: &String, filename: &String) {
+    let home = dirs::home_dir().unwrap();
+    let path = home.join(".studio").join(env);
+
+    if std::fs::remove_file(path.join(filename)).is_ok() {
+        info!("File removed from disk {}", path.join(filename).display());
+    }
+}
@@ -0,0 +1,1216 @@
+use crate::output::{OutputFormat, Resource};
+use clap::{Args, Parser, Subcommand};
+use livepeer_rs::{
+    ai::Generate,
+    playback::Playback,
+    user::UserTrait,
+    vod::{Task, Vod},
+};
+
+#[derive(Parser)]
+#[command(name = "studio", version, about = "Simple CLI for Livepeer Studio")]
+pub struct Cli {
+    /// Environment to interact with
+    #[arg(
+        long,
+        global = true,
+        default_value = "prod",
+        env = "LIVEPEER_STUDIO_ENV",
+        value_parser = clap::builder::PossibleValuesParser::new(crate::api::ENVS)
+    )]
+    pub env: String,
+
+    /// API key to use, defaults to the only key saved under ~/.studio/<env>
+    #[arg(
+        long,
+        global = true,
+        env = "LIVEPEER_STUDIO_API_KEY",
+        hide_env_values = true
+    )]
+    pub api_key: Option<String>,
+
+    /// Output format of subcommand results
+    #[arg(long, short, global = true, value_enum, default_value = "json")]
+    pub output: OutputFormat,
+
+    /// Bandwidth limit for uploads and pushes in bytes per second, with K, M or G suffix (e.g. 20M)
+    #[arg(long, global = true, env = "LIVEPEER_STUDIO_LIMIT_RATE", value_parser = crate::ratelimit::parse_rate)]
+    pub limit_rate: Option<u64>,
+
+    /// Without a subcommand the interactive menu is shown
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Users
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Streams
+    #[command(subcommand)]
+    Streams(StreamsCommand),
+    /// Assets
+    #[command(subcommand)]
+    Assets(AssetsCommand),
+    /// Tasks
+    #[command(subcommand)]
+    Tasks(TasksCommand),
+    /// Get playback info by playback ID or CID
+    Playback { playback_id: String },
+    /// AI generation
+    #[command(subcommand)]
+    Ai(AiCommand),
+}
+
+#[derive(Subcommand)]
+pub enum UsersCommand {
+    /// Info of the user owning the API key
+    Me,
+    /// Get user info by ID
+    Get { user_id: String },
+}
+
+#[derive(Subcommand)]
+pub enum StreamsCommand {
+    /// List streams of the current user, or of another user with --user-id
+    List {
+        #[command(flatten)]
+        user: UserIdArgs,
+        /// Print full stream keys instead of redacted ones
+        #[arg(long)]
+        show_keys: bool,
+    },
+    /// Get a stream by ID
+    Get { stream_id: String },
+    /// Get a stream by playback ID
+    GetByPlaybackId { playback_id: String },
+    /// Create a stream
+    Create {
+        #[arg(long)]
+        name: String,
+        /// Transcoding profile name:WIDTHxHEIGHT:bitrate[:fps[:gop]], repeat for a ladder
+        #[arg(long = "profile", value_parser = crate::live::parse_profile)]
+        profiles: Vec<crate::live::StreamProfile>,
+        /// Use the profiles of a built-in or saved preset
+        #[arg(long, conflicts_with = "profiles")]
+        preset: Option<String>,
+        /// Record the stream
+        #[arg(long)]
+        record: bool,
+        /// Creator ID stored on the stream
+        #[arg(long)]
+        creator_id: Option<String>,
+        /// User tag key=value, can be repeated
+        #[arg(long = "tag", value_parser = crate::live::parse_tag)]
+        tags: Vec<(String, String)>,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Rename a stream, turn recording on or off, or (un)suspend it
+    Update {
+        stream_id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        record: Option<bool>,
+        /// Suspended streams reject pushes and playback
+        #[arg(long)]
+        suspended: Option<bool>,
+    },
+    /// Terminate the active session of a stream
+    Terminate { stream_id: String },
+    /// Delete a stream
+    Delete { stream_id: String },
+    /// Replace the stream key of a stream by a new stream with the same settings (new id and playback id)
+    RotateKey { stream_id: String },
+    /// Replace the stream keys of every stream of the current user, or of another user with --user-id
+    RotateKeys(UserIdArgs),
+    /// Clip the last seconds of a live stream, the part between two wall clock times, or part of
+    /// the recording of a session with --session
+    Clip {
+        stream_id: String,
+        /// Seconds back from the live edge
+        #[arg(long, conflicts_with_all = ["start", "end", "session"], required_unless_present_all = ["start", "end"])]
+        last: Option<f64>,
+        /// RFC 3339 or HH:MM:SS today, with --session the position in the recording (seconds,
+        /// MM:SS or HH:MM:SS)
+        #[arg(long, requires = "end")]
+        start: Option<String>,
+        #[arg(long, requires = "start")]
+        end: Option<String>,
+        /// Clip the recording of this session of the stream
+        #[arg(long)]
+        session: Option<String>,
+        #[command(flatten)]
+        clip: ClipArgs,
+    },
+    /// List past sessions of a stream and their recordings
+    Sessions { stream_id: String },
+    /// Import the recording of a session as an asset
+    SaveRecording {
+        session_id: String,
+        /// Asset name, defaults to the session start time
+        #[arg(long)]
+        name: Option<String>,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Change the playback policy of a stream
+    SetPolicy {
+        stream_id: String,
+        /// New playback policy: public, jwt or webhook:<webhook id>
+        #[arg(long)]
+        policy: String,
+    },
+    /// Manage transcoding profile presets
+    #[command(subcommand)]
+    Presets(PresetsCommand),
+    /// Push a file, or a generated test pattern, to a stream in the background
+    Push {
+        stream_id: String,
+        #[arg(required_unless_present = "test_source")]
+        file: Option<String>,
+        #[arg(long, default_value = "fra", value_parser = clap::builder::PossibleValuesParser::new(crate::live::REGIONS))]
+        region: String,
+        /// Push the file again from the start when it ends, until the push is stopped
+        #[arg(long = "loop", conflicts_with = "test_source")]
+        looped: bool,
+        /// Push a test pattern with the wall clock burned in and a tone instead of a file
+        #[arg(long, conflicts_with = "file")]
+        test_source: bool,
+        /// Test pattern resolution, with --test-source
+        #[arg(long, default_value = "1280x720")]
+        size: String,
+        /// Test pattern frame rate, with --test-source
+        #[arg(long, default_value_t = 30)]
+        fps: u32,
+        /// Test pattern duration in seconds, 0 to push until stopped, with --test-source
+        #[arg(long, default_value_t = 0)]
+        duration: u64,
+    },
+    /// Manage pushes running in the background
+    #[command(subcommand)]
+    Pushes(PushesCommand),
+    /// Manage multistream targets and restream streams to them
+    #[command(subcommand)]
+    Targets(TargetsCommand),
+}
+
+#[derive(Subcommand)]
+pub enum PushesCommand {
+    /// List pushes and whether they're still running
+    List {
+        #[arg(long)]
+        stream_id: Option<String>,
+    },
+    /// Print the ffmpeg output of a push
+    Logs {
+        push_id: String,
+        /// Number of lines from the end
+        #[arg(long, default_value_t = 20)]
+        lines: usize,
+        /// Keep printing the output until the push exits
+        #[arg(long)]
+        follow: bool,
+    },
+    /// Stop a push, or every push with --all
+    Stop {
+        #[arg(required_unless_present = "all")]
+        push_id: Option<String>,
+        #[arg(long, conflicts_with = "push_id")]
+        all: bool,
+        /// Only stop the pushes of this stream with --all
+        #[arg(long, requires = "all")]
+        stream_id: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TargetsCommand {
+    /// List multistream targets
+    List,
+    /// Create a target from an rtmp://, rtmps:// or srt:// ingest url with its stream key
+    Create {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        url: String,
+    },
+    /// Update the name, url or disabled flag of a target
+    Update {
+        target_id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        url: Option<String>,
+        #[arg(long)]
+        disabled: Option<bool>,
+    },
+    /// Delete a target
+    Delete { target_id: String },
+    /// Restream a stream to a target
+    Attach {
+        stream_id: String,
+        target_id: String,
+        /// Rendition to restream, a profile name of the stream or source
+        #[arg(long, default_value = crate::multistream::SOURCE_PROFILE)]
+        profile: String,
+        /// Drop the audio
+        #[arg(long)]
+        video_only: bool,
+    },
+    /// Stop restreaming a stream to a target
+    Detach {
+        stream_id: String,
+        target_id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PresetsCommand {
+    /// List built-in and saved presets
+    List,
+    /// Show the profiles of a preset
+    Get { name: String },
+    /// Write saved presets, or the given ones, to a json file
+    Export {
+        path: String,
+        /// Preset to export, can be repeated
+        #[arg(long = "name")]
+        names: Vec<String>,
+    },
+    /// Save the presets of a json file, replacing saved presets with the same name
+    Import { path: String },
+    /// Delete a saved preset
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum AssetsCommand {
+    /// List assets of the current user, or of another user with --user-id
+    List(UserIdArgs),
+    /// Get an asset by ID or playback ID
+    Get { asset_id: String },
+    /// Get assets by CID
+    GetByCid { cid: String },
+    /// Upload a local file, or stdin with - or a named pipe
+    Upload {
+        path: String,
+        /// Asset name, defaults to the file name
+        #[arg(long)]
+        name: Option<String>,
+        /// Remux or transcode the file with ffmpeg before uploading it
+        #[arg(long, value_enum)]
+        convert: Option<crate::assets::upload::convert::ConvertMode>,
+        /// Maximum height of the transcoded video
+        #[arg(long, requires = "convert")]
+        max_height: Option<u64>,
+        #[command(flatten)]
+        upload: UploadArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Upload every video file in a folder or matching a glob
+    UploadBatch {
+        folder_or_glob: String,
+        /// Include files in subfolders
+        #[arg(long)]
+        recursive: bool,
+        /// Number of files uploaded at the same time
+        #[arg(long, default_value_t = 2)]
+        concurrency: usize,
+        #[command(flatten)]
+        upload: UploadArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Watch a folder and upload new video files once they stop growing
+    Watch {
+        folder: String,
+        /// Seconds between two scans of the folder
+        #[arg(long, default_value_t = 5)]
+        interval: u64,
+        /// Scans a file size must stay unchanged before it is uploaded
+        #[arg(long, default_value_t = 2)]
+        settle: u32,
+        /// What to do with a file once processed
+        #[arg(long, value_enum, default_value = "move")]
+        on_done: crate::assets::upload::watch::DoneAction,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Manage the local queue of uploads
+    #[command(subcommand)]
+    Queue(QueueCommand),
+    /// Import an asset from a URL
+    Import {
+        url: String,
+        #[arg(long, default_value = "livepeer_rs_import")]
+        name: String,
+        /// What to do when checking the url finds problems before importing it
+        #[arg(long, value_enum, default_value = "warn")]
+        preflight: crate::assets::upload::probe::PreflightAction,
+        #[command(flatten)]
+        retry: RetryArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Import every url of a csv or json manifest (url, name, policy, metadata)
+    ImportManifest {
+        manifest: String,
+        /// Number of imports running at the same time
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+        /// Don't wait for the import tasks to complete
+        #[arg(long)]
+        no_wait: bool,
+        /// Also write the results to this csv or json file
+        #[arg(long)]
+        results: Option<String>,
+        /// What to do when checking a url finds problems, ask is the same as warn
+        #[arg(long, value_enum, default_value = "warn")]
+        preflight: crate::assets::upload::probe::PreflightAction,
+        #[command(flatten)]
+        retry: RetryArgs,
+        #[command(flatten)]
+        policy: PolicyArgs,
+    },
+    /// Export an asset to IPFS
+    ExportIpfs { asset_id: String },
+    /// Change the playback policy of an asset
+    SetPolicy {
+        asset_id: String,
+        /// New playback policy: public, jwt or webhook:<webhook id>
+        #[arg(long)]
+        policy: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QueueCommand {
+    /// List queued uploads
+    List,
+    /// Upload every pending entry
+    Run,
+    /// Queue a failed, interrupted or cancelled upload again and run it
+    Retry { id: String },
+    /// Cancel a pending or failed upload
+    Cancel { id: String },
+    /// Remove completed and cancelled uploads
+    Purge {
+        /// Remove failed uploads too
+        #[arg(long)]
+        failed: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TasksCommand {
+    /// List tasks of the current user, or of another user with --user-id
+    List(UserIdArgs),
+    /// Get a task by ID
+    Get { task_id: String },
+    /// Track a task until it completes or fails
+    Watch { task_id: String },
+}
+
+#[derive(Subcommand)]
+pub enum AiCommand {
+    /// Generate an image from a prompt
+    TextToImage { prompt: String },
+}
+
+#[derive(Args)]
+pub struct UserIdArgs {
+    #[arg(long)]
+    user_id: Option<String>,
+}
+
+#[derive(Args)]
+pub struct PolicyArgs {
+    /// Playback policy: public, jwt or webhook:<webhook id>
+    #[arg(long, default_value = "public")]
+    policy: String,
+}
+
+#[derive(Args)]
+pub struct ClipArgs {
+    /// Clip name
+    #[arg(long)]
+    name: Option<String>,
+    /// Wait for the clip to be processed
+    #[arg(long)]
+    wait: bool,
+}
+
+#[derive(Args)]
+pub struct UploadArgs {
+    /// Upload with tus, resuming a previously interrupted upload of the same file
+    #[arg(long)]
+    resumable: bool,
+    /// What to do when the same content was already uploaded to this env
+    #[arg(long, value_enum, default_value = "skip")]
+    on_duplicate: crate::assets::upload::dedup::DuplicateAction,
+    /// What to do when ffprobe finds problems in a file before uploading it
+    #[arg(long, value_enum, default_value = "warn")]
+    preflight: crate::assets::upload::probe::PreflightAction,
+    #[command(flatten)]
+    retry: RetryArgs,
+}
+
+#[derive(Args)]
+pub struct RetryArgs {
+    /// Tries for each request before giving up, 1 disables retries
+    #[arg(long, default_value_t = 4)]
+    attempts: u32,
+    /// Milliseconds to wait before the first retry, doubled at every retry
+    #[arg(long, default_value_t = 1000)]
+    backoff: u64,
+    /// Maximum milliseconds to wait between two retries
+    #[arg(long, default_value_t = 30000)]
+    max_backoff: u64,
+    /// Fraction of the wait randomly added or removed
+    #[arg(long, default_value_t = 0.25)]
+    jitter: f64,
+}
+
+impl UploadArgs {
+    fn options(&self) -> crate::assets::upload::UploadOptions {
+        crate::assets::upload::UploadOptions {
+            resumable: self.resumable,
+            on_duplicate: self.on_duplicate,
+            preflight: self.preflight,
+            retry: self.retry.policy(),
+        }
+    }
+}
+
+impl RetryArgs {
+    fn policy(&self) -> crate::assets::upload::retry::RetryPolicy {
+        crate::assets::upload::retry::RetryPolicy {
+            attempts: self.attempts,
+            initial_backoff_ms: self.backoff,
+            max_backoff_ms: self.max_backoff,
+            jitter: self.jitter,
+        }
+    }
+}
+
+impl PolicyArgs {
+    fn playback_policy(&self) -> Result<Option<serde_json::Value>, String> {
+        crate::accesscontrol::parse_playback_policy(&self.policy)
+    }
+}
+
+// Run a single subcommand and return the process exit code
+pub fn run(command: Command, env: &String, api_key: Option<String>, output: OutputFormat) -> i32 {
+    let api_key = match api_key.or_else(|| crate::auth::load_default_api_key(env)) {
+        Some(k) => k,
+        None => {
+            error!("No API key available for env {}", env);
+            return 1;
+        }
+    };
+
+    info!("Initializing livepeer client on env {}", env);
+    crate::auth::set_current_env(env);
+    crate::auth::set_current_api_key(&api_key);
+
+    let client = match livepeer_rs::Livepeer::new(Some(api_key), Some(crate::api::lvpr_env(env))) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("Unable to initialize client: {}", e);
+            return 1;
+        }
+    };
+
+    let resource = resource(&command);
+    let result = match command {
+        Command::Users(c) => users(c, &client),
+        Command::Streams(c) => streams(c, &client),
+        Command::Assets(c) => assets(c, &client),
+        Command::Tasks(c) => tasks(c, &client),
+        Command::Playback { playback_id } => client
+            .playback
+            .get_playback_info(&playback_id)
+            .map_err(|e| format!("Error getting playback info: {:?}", e)),
+        Command::Ai(AiCommand::TextToImage { prompt }) => client
+            .generate
+            .text_to_image(&prompt)
+            .map(|r| serde_json::json!(r))
+            .map_err(|e| format!("Error generating image: {:?}", e)),
+    };
+
+    match result {
+        Ok(v) => {
+            println!("{}", crate::output::render(&v, resource, output));
+            if has_failures(&v) {
+                1
+            } else {
+                0
+            }
+        }
+        Err(e) => {
+            error!("{}", e);
+            1
+        }
+    }
+}
+
+fn resource(command: &Command) -> Resource {
+    match command {
+        Command::Users(_) => Resource::User,
+        Command::Streams(StreamsCommand::Create { .. })
+        | Command::Streams(StreamsCommand::SetPolicy { .. })
+        | Command::Streams(StreamsCommand::Terminate { .. })
+        | Command::Streams(StreamsCommand::Delete { .. }) => Resource::Raw,
+        Command::Streams(StreamsCommand::Targets(TargetsCommand::Delete { .. })) => Resource::Raw,
+        Command::Streams(StreamsCommand::Targets(TargetsCommand::Attach { .. }))
+        | Command::Streams(StreamsCommand::Targets(TargetsCommand::Detach { .. })) => {
+            Resource::StreamTarget
+        }
+        Command::Streams(StreamsCommand::Targets(_)) => Resource::Target,
+        Command::Streams(StreamsCommand::Sessions { .. }) => Resource::Session,
+        Command::Streams(StreamsCommand::Push { .. })
+        | Command::Streams(StreamsCommand::Pushes(PushesCommand::List { .. }))
+        | Command::Streams(StreamsCommand::Pushes(PushesCommand::Stop { .. })) => Resource::Push,
+        Command::Streams(StreamsCommand::Pushes(_)) => Resource::Raw,
+        Command::Streams(StreamsCommand::Clip { .. }) => Resource::Raw,
+        Command::Streams(StreamsCommand::SaveRecording { .. }) => Resource::Raw,
+        Command::Streams(StreamsCommand::RotateKey { .. })
+        | Command::Streams(StreamsCommand::RotateKeys(_)) => Resource::StreamKey,
+        Command::Streams(StreamsCommand::Presets(PresetsCommand::List)) => Resource::Preset,
+        Command::Streams(StreamsCommand::Presets(_)) => Resource::Raw,
+        Command::Streams(_) => Resource::Stream,
+        Command::Assets(AssetsCommand::List(_))
+        | Command::Assets(AssetsCommand::Get { .. })
+        | Command::Assets(AssetsCommand::GetByCid { .. }) => Resource::Asset,
+        Command::Assets(AssetsCommand::UploadBatch { .. }) => Resource::Upload,
+        Command::Assets(AssetsCommand::ImportManifest { .. }) => Resource::Import,
+        Command::Assets(AssetsCommand::Queue(QueueCommand::Purge { .. })) => Resource::Raw,
+        Command::Assets(AssetsCommand::Queue(_)) => Resource::Queue,
+        Command::Assets(_) => Resource::Raw,
+        Command::Tasks(_) => Resource::Task,
+        Command::Playback { .. } => Resource::Playback,
+        Command::Ai(_) => Resource::Raw,
+    }
+}
+
+// Batch results are printed even when some items failed, the exit code still reports them
+fn has_failures(value: &serde_json::Value) -> bool {
+    value
+        .as_array()
+        .map_or(false, |items| items.iter().any(|i| i["status"] == "failed"))
+}
+
+fn users(
+    command: UsersCommand,
+    client: &livepeer_rs::Livepeer,
+) -> Result<serde_json::Value, String> {
+    match command {
+        UsersCommand::Me => Ok(serde_json::to_value(&client.user.info).unwrap()),
+        UsersCommand::Get { user_id } => client
+            .user_api
+            .get_user_info_by_id(user_id)
+            .map_err(|e| format!("Error getting user info: {:?}", e)),
+    }
+}
+
+fn streams(
+    command: StreamsCommand,
+    client: &livepeer_rs::Livepeer,
+) -> Result<serde_json::Value, String> {
+    match command {
+        StreamsCommand::List { user, show_keys } => {
+            let user_id = user.user_id.unwrap_or(client.user.user_id.clone());
+            client
+                .stream
+                .clone()
+                .get_streams_by_user_id(user_id)
+                .map(|list| {
+                    list.iter()
+                        .map(|x| {
+                            serde_json::json!({
+                                "id": x.id,
+                                "name": x.name,
+                                "streamKey": if show_keys {
+                                    x.stream_key.clone()
+                                } else {
+                                    x.stream_key.as_deref().map(crate::live::redact_key)
+                                },
+                                "playbackId": x.playback_id,
+                                "isActive": x.is_active,
+                            })
+                        })
+                        .collect()
+                })
+                .map_err(|e| format!("Error getting streams: {:?}", e))
+        }
+        StreamsCommand::Get { stream_id } => client
+            .stream
+            .clone()
+            .get_stream_by_id(stream_id)
+            .map_err(|e| format!("Error getting stream: {:?}", e)),
+        StreamsCommand::GetByPlaybackId { playback_id } => client
+            .stream
+            .clone()
+            .get_stream_by_playback_id(playback_id, client.user.info.admin)
+            .map_err(|e| format!("Error getting stream: {:?}", e)),
+        StreamsCommand::Create {
+            name,
+            profiles,
+            preset,
+            record,
+            creator_id,
+            tags,
+            policy,
+        } => {
+            let profiles = match preset {
+                Some(p) => crate::presets::find(&p)?.profiles,
+                None if profiles.is_empty() => crate::live::default_profiles(),
+                None => profiles,
+            };
+            let new_stream = crate::live::NewStream {
+                name,
+                profiles,
+                record,
+                creator_id,
+                user_tags: tags
+                    .into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::from(v)))
+                    .collect(),
+                playback_policy: policy.playback_policy()?,
+            };
+            crate::live::create_stream(&new_stream).map(|s| crate::live::stream_summary(&s))
+        }
+        StreamsCommand::Update {
+            stream_id,
+            name,
+            record,
+            suspended,
+        } => {
+            let mut fields = serde_json::Map::new();
+            if let Some(name) = name {
+                fields.insert(String::from("name"), serde_json::json!(name));
+            }
+            if let Some(record) = record {
+                fields.insert(String::from("record"), serde_json::json!(record));
+            }
+            if let Some(suspended) = suspended {
+                fields.insert(String::from("suspended"), serde_json::json!(suspended));
+            }
+            if fields.is_empty() {
+                return Err(String::from(
+                    "Nothing to update, pass --name, --record or --suspended",
+                ));
+            }
+            crate::live::update_stream(&stream_id, serde_json::Value::Object(fields))
+        }
+        StreamsCommand::Terminate { stream_id } => crate::live::terminate_stream(&stream_id)
+            .map(|_| serde_json::json!({ "terminated": stream_id })),
+        StreamsCommand::Delete { stream_id } => crate::live::delete_stream(&stream_id)
+            .map(|_| serde_json::json!({ "deleted": stream_id })),
+        StreamsCommand::Clip {
+            stream_id,
+            last,
+            start,
+            end,
+            session,
+            clip,
+        } => {
+            let stream = client
+                .stream
+                .clone()
+                .get_stream_by_id(stream_id.clone())
+                .map_err(|e| format!("Error getting stream: {:?}", e))?;
+            let (start_time, end_time) = match (last, start, end, &session) {
+                (Some(seconds), _, _, _) => crate::clips::last(seconds),
+                (None, Some(start), Some(end), Some(session_id)) => {
+                    let session = crate::sessions::get_session(session_id)?;
+                    if session["parentId"].as_str() != Some(stream_id.as_str()) {
+                        return Err(format!(
+                            "Session {} is not a session of stream {}",
+                            session_id, stream_id
+                        ));
+                    }
+                    crate::clips::recording_window(
+                        &session,
+                        crate::clips::parse_offset(&start)?,
+                        crate::clips::parse_offset(&end)?,
+                    )
+                }
+                (None, Some(start), Some(end), None) => (
+                    crate::clips::parse_wall_clock(&start)?,
+                    crate::clips::parse_wall_clock(&end)?,
+                ),
+                _ => return Err(String::from("Pass --last or --start and --end")),
+            };
+            create_clip(
+                client,
+                &stream,
+                session.as_deref(),
+                start_time,
+                end_time,
+                clip,
+            )
+        }
+        StreamsCommand::Sessions { stream_id } => crate::sessions::list_sessions(&stream_id)
+            .map(|list| list.iter().map(crate::sessions::session_summary).collect()),
+        StreamsCommand::SaveRecording {
+            session_id,
+            name,
+            policy,
+        } => {
+            let session = crate::sessions::get_session(&session_id)?;
+            let name = name.unwrap_or(format!(
+                "Recording {}",
+                crate::output::format_timestamp(&session["createdAt"])
+            ));
+            crate::sessions::save_recording(&session, name, policy.playback_policy()?)
+        }
+        StreamsCommand::RotateKey { stream_id } => crate::live::rotate_stream_key(&stream_id),
+        StreamsCommand::RotateKeys(args) => crate::live::rotate_stream_keys(
+            client,
+            args.user_id.unwrap_or(client.user.user_id.clone()),
+        )
+        .map(serde_json::Value::from),
+        StreamsCommand::SetPolicy { stream_id, policy } => {
+            set_policy("stream", &stream_id, &policy)
+        }
+        StreamsCommand::Presets(command) => presets(command),
+        StreamsCommand::Targets(command) => targets(command),
+        StreamsCommand::Push {
+            stream_id,
+            file,
+            region,
+            looped,
+            test_source,
+            size,
+            fps,
+            duration,
+        } => {
+            let (source, input_args) = match file {
+                Some(file) if !test_source => {
+                    let input_args = crate::pushes::file_input(&file, looped);
+                    (file, input_args)
+                }
+                _ => {
+                    let (width, height) = crate::pushes::parse_size(&size)?;
+                    if fps == 0 {
+                        return Err(String::from("The frame rate must be positive"));
+                    }
+                    let test_source = crate::pushes::TestSource {
+                        width,
+                        height,
+                        fps,
+                        duration,
+                    };
+                    (test_source.label(), test_source.input_args())
+                }
+            };
+            let stream = client
+                .stream
+                .clone()
+                .get_stream_by_id(stream_id)
+                .map_err(|e| format!("Error getting stream: {:?}", e))?;
+            crate::pushes::start(&stream, &region, &source, input_args)
+                .map(|p| crate::pushes::to_value(&vec![p]))
+        }
+        StreamsCommand::Pushes(command) => pushes(command),
+    }
+}
+
+// Clip a stream, waiting for the clip to be processed with --wait
+fn create_clip(
+    client: &livepeer_rs::Livepeer,
+    stream: &serde_json::Value,
+    session_id: Option<&str>,
+    start_time: i64,
+    end_time: i64,
+    args: ClipArgs,
+) -> Result<serde_json::Value, String> {
+    let clip = crate::clips::create_clip(
+        stream["playbackId"].as_str().unwrap_or(""),
+        session_id,
+        start_time,
+        end_time,
+        args.name,
+    )?;
+    let mut result = serde_json::json!({
+        "assetId": clip["asset"]["id"],
+        "taskId": clip["task"]["id"],
+        "playbackId": clip["asset"]["playbackId"],
+        "status": "created",
+    });
+    if args.wait {
+        let task_id = clip["task"]["id"].as_str().unwrap_or("").to_string();
+        let pb = crate::assets::upload::progress::new_task_bar(&String::from("clip"));
+        crate::tasks::wait_for_task(client, &task_id, &pb)?;
+        result["status"] = serde_json::json!("completed");
+    }
+    Ok(result)
+}
+
+// Change the policy of a stream or asset, printing what changed to stderr
+fn set_policy(kind: &str, id: &String, policy: &String) -> Result<serde_json::Value, String> {
+    let playback_policy = crate::accesscontrol::parse_playback_policy(policy)?;
+    let before = crate::api::get(&format!("/api/{}/{}", kind, id))?["playbackPolicy"].clone();
+    let updated = crate::accesscontrol::set_playback_policy(kind, id, playback_policy)?;
+    let after = updated["playbackPolicy"].clone();
+    crate::accesscontrol::print_policy_diff(&before, &after);
+    Ok(serde_json::json!({ "id": id, "before": before, "after": after }))
+}
+
+fn pushes(command: PushesCommand) -> Result<serde_json::Value, String> {
+    use crate::pushes;
+
+    match command {
+        PushesCommand::List { stream_id } => {
+            Ok(pushes::to_value(&pushes::list(stream_id.as_deref())))
+        }
+        PushesCommand::Logs {
+            push_id,
+            lines,
+            follow,
+        } => {
+            if follow {
+                pushes::follow(&push_id)?;
+                return Ok(serde_json::Value::Null);
+            }
+            pushes::tail(&push_id, lines).map(serde_json::Value::from)
+        }
+        PushesCommand::Stop {
+            push_id: Some(push_id),
+            ..
+        } => pushes::stop(&push_id).map(|p| pushes::to_value(&vec![p])),
+        PushesCommand::Stop { stream_id, .. } => {
+            let mut stopped = vec![];
+            for result in pushes::stop_all(stream_id.as_deref()) {
+                match result {
+                    Ok(p) => stopped.push(p),
+                    Err(e) => error!("{}", e),
+                }
+            }
+            Ok(pushes::to_value(&stopped))
+        }
+    }
+}
+
+fn targets(command: TargetsCommand) -> Result<serde_json::Value, String> {
+    use crate::multistream;
+
+    match command {
+        TargetsCommand::List => multistream::list_targets(),
+        TargetsCommand::Create { name, url } => multistream::create_target(&name, &url),
+        TargetsCommand::Update {
+            target_id,
+            name,
+            url,
+            disabled,
+        } => {
+            let mut fields = serde_json::Map::new();
+            if let Some(name) = name {
+                fields.insert(String::from("name"), serde_json::json!(name));
+            }
+            if let Some(url) = url {
+                fields.insert(String::from("url"), serde_json::json!(url));
+            }
+            if let Some(disabled) = disabled {
+                fields.insert(String::from("disabled"), serde_json::json!(disabled));
+            }
+            if fields.is_empty() {
+                return Err(String::from(
+                    "Nothing to update, pass --name, --url or --disabled",
+                ));
+            }
+            multistream::update_target(&target_id, serde_json::Value::Object(fields))
+        }
+        TargetsCommand::Delete { target_id } => multistream::delete_target(&target_id)
+            .map(|_| serde_json::json!({ "deleted": target_id })),
+        TargetsCommand::Attach {
+            stream_id,
+            target_id,
+            profile,
+            video_only,
+        } => multistream::attach(&stream_id, &target_id, &profile, video_only)
+            .map(|s| s["multistream"]["targets"].clone()),
+        TargetsCommand::Detach {
+            stream_id,
+            target_id,
+        } => {
+            multistream::detach(&stream_id, &target_id).map(|s| s["multistream"]["targets"].clone())
+        }
+    }
+}
+
+fn presets(command: PresetsCommand) -> Result<serde_json::Value, String> {
+    match command {
+        PresetsCommand::List => Ok(crate::presets::to_value(&crate::presets::all())),
+        PresetsCommand::Get { name } => {
+            crate::presets::find(&name).map(|p| serde_json::to_value(&p.profiles).unwrap())
+        }
+        PresetsCommand::Export { path, names } => crate::presets::export(&path, &names)
+            .map(|count| serde_json::json!({ "exported": count, "path": path })),
+        PresetsCommand::Import { path } => crate::presets::import(&path)
+            .map(|count| serde_json::json!({ "imported": count, "path": path })),
+        PresetsCommand::Delete { name } => {
+            crate::presets::remove(&name).map(|_| serde_json::json!({ "deleted": name }))
+        }
+    }
+}
+
+fn assets(
+    command: AssetsCommand,
+    client: &livepeer_rs::Livepeer,
+) -> Result<serde_json::Value, String> {
+    match command {
+        AssetsCommand::List(args) => match args.user_id {
+            Some(user_id) => client.asset.get_assets_by_user_id(user_id),
+            None => client.asset.list_paginated_assets(10000, 0, true),
+        }
+        .map_err(|e| format!("Error getting assets: {:?}", e)),
+        AssetsCommand::Get { asset_id } => {
+            crate::assets::get_asset_by_id_or_playback_id(client, asset_id)
+                .ok_or(String::from("Asset not found"))
+        }
+        AssetsCommand::GetByCid { cid } => client
+            .asset
+            .get_assets_by_cid(cid, client.user.info.admin)
+            .map_err(|e| format!("Error getting assets: {:?}", e)),
+        AssetsCommand::Upload {
+            path,
+            name,
+            convert,
+            max_height,
+            upload,
+            policy,
+        } => {
+            let playback_policy = policy.playback_policy()?;
+            if crate::assets::upload::stream::is_stream(&path) {
+                if convert.is_some() {
+                    return Err(String::from("--convert needs a file, not stdin or a pipe"));
+                }
+                let asset_name = match name {
+                    Some(n) => n,
+                    None if path == crate::assets::upload::stream::STDIN => {
+                        return Err(String::from("--name is required when uploading from stdin"))
+                    }
+                    None => std::path::Path::new(&path)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                return crate::assets::upload::stream::upload_stream(
+                    &path,
+                    asset_name,
+                    playback_policy,
+                    &upload.options(),
+                )
+                .map(|r| {
+                    serde_json::json!({
+                        "assetId": r.asset_id,
+                        "taskId": r.task_id,
+                        "playbackId": r.playback_id,
+                        "duplicate": r.duplicate,
+                    })
+                });
+            }
+            if !std::path::Path::new(&path).is_file() {
+                return Err(format!("File not found: {}", path));
+            }
+            let asset_name = name.unwrap_or_else(|| {
+                std::path::Path::new(&path)
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            });
+            let converted = match convert {
+                Some(mode) => {
+                    let info = crate::assets::upload::probe::probe(&path)?;
+                    Some(crate::assets::upload::convert::convert(
+                        &path, mode, &info, max_height,
+                    )?)
+                }
+                None => None,
+            };
+            crate::assets::upload::queue::upload_converted(
+                client,
+                &path,
+                converted,
+                asset_name,
+                playback_policy,
+                upload.options(),
+            )
+            .map(|r| {
+                serde_json::json!({
+                    "assetId": r.asset_id,
+                    "taskId": r.task_id,
+                    "playbackId": r.playback_id,
+                    "duplicate": r.duplicate,
+                })
+            })
+            .ok_or(String::from("Upload failed"))
+        }
+        AssetsCommand::UploadBatch {
+            folder_or_glob,
+            recursive,
+            concurrency,
+            upload,
+            policy,
+        } => {
+            let playback_policy = policy.playback_policy()?;
+            let files = crate::assets::upload::batch::collect_files(&folder_or_glob, recursive)?;
+            if files.is_empty() {
+                return Err(format!("No video files found in {}", folder_or_glob));
+            }
+            let results = crate::assets::upload::batch::upload_files(
+                client,
+                files,
+                playback_policy,
+                concurrency,
+                &upload.options(),
+            );
+            Ok(crate::assets::upload::batch::summary(&results))
+        }
+        AssetsCommand::Watch {
+            folder,
+            interval,
+            settle,
+            on_done,
+            policy,
+        } => {
+            let playback_policy = policy.playback_policy()?;
+            if !std::path::Path::new(&folder).is_dir() {
+                return Err(format!("Folder not found: {}", folder));
+            }
+            crate::assets::upload::watch::watch_folder(
+                client,
+                &folder,
+                std::time::Duration::from_secs(interval),
+                settle,
+                on_done,
+                playback_policy,
+            );
+            Ok(serde_json::Value::Null)
+        }
+        AssetsCommand::Queue(command) => upload_queue(command, client),
+        AssetsCommand::Import {
+            url,
+            name,
+            preflight,
+            retry,
+            policy,
+        } => {
+            let playback_policy = policy.playback_policy()?;
+            if !crate::assets::upload::urlcheck::preflight(&url, preflight) {
+                return Err(format!("Not importing {}", url));
+            }
+            retry.policy().run("Importing asset", || {
+                crate::assets::upload::import_url(&url, &name, &playback_policy)
+            })
+        }
+        AssetsCommand::ImportManifest {
+            manifest,
+            concurrency,
+            no_wait,
+            results,
+            preflight,
+            retry,
+            policy,
+        } => {
+            use crate::assets::upload::manifest;
+            let playback_policy = policy.playback_policy()?;
+            let entries = manifest::read_manifest(&manifest)?;
+            if entries.is_empty() {
+                return Err(format!("No entries in {}", manifest));
+            }
+            let imported = manifest::import_manifest(
+                client,
+                entries,
+                playback_policy,
+                concurrency,
+                &retry.policy(),
+                !no_wait,
+                preflight,
+            );
+            if let Some(path) = results {
+                manifest::write_results(&path, &imported)?;
+            }
+            Ok(manifest::to_value(&imported))
+        }
+        AssetsCommand::ExportIpfs { asset_id } => client
+            .asset
+            .export_to_ipfs(asset_id, String::from("{}"))
+            .map_err(|e| format!("Error exporting to ipfs: {:?}", e)),
+        AssetsCommand::SetPolicy { asset_id, policy } => set_policy("asset", &asset_id, &policy),
+    }
+}
+
+fn upload_queue(
+    command: QueueCommand,
+    client: &livepeer_rs::Livepeer,
+) -> Result<serde_json::Value, String> {
+    use crate::assets::upload::queue;
+
+    match command {
+        QueueCommand::List => Ok(queue::to_value(&queue::list()?)),
+        QueueCommand::Run => {
+            let ids = queue::pending()?
+                .into_iter()
+                .map(|e| e.id)
+                .collect::<Vec<String>>();
+            for id in &ids {
+                queue::run_entry(client, id);
+            }
+            let entries = queue::list()?
+                .into_iter()
+                .filter(|e| ids.contains(&e.id))
+                .collect();
+            Ok(queue::to_value(&entries))
+        }
+        QueueCommand::Retry { id } => {
+            queue::retry(&id)?;
+            queue::run_entry(client, &id);
+            let entries = queue::list()?.into_iter().filter(|e| e.id == id).collect();
+            Ok(queue::to_value(&entries))
+        }
+        QueueCommand::Cancel { id } => {
+            queue::cancel(&id)?;
+            let entries = queue::list()?.into_iter().filter(|e| e.id == id).collect();
+            Ok(queue::to_value(&entries))
+        }
+        QueueCommand::Purge { failed } => {
+            Ok(serde_json::json!({ "purged": queue::purge(failed)? }))
+        }
+    }
+}
+
+fn tasks(
+    command: TasksCommand,
+    client: &livepeer_rs::Livepeer,
+) -> Result<serde_json::Value, String> {
+    match command {
+        TasksCommand::List(args) => match args.user_id {
+            Some(user_id) => client.task.get_tasks_by_user_id(user_id),
+            None => client.task.list_tasks(),
+        }
+        .map_err(|e| format!("Error getting tasks: {:?}", e)),
+        TasksCommand::Get { task_id } => client
+            .task
+            .get_task_by_id(task_id)
+            .map_err(|e| format!("Error getting task: {:?}", e)),
+        TasksCommand::Watch { task_id } => {
+            if !crate::tasks::track_task_status(serde_json::json!({ "id": task_id }), client) {
+                return Err(format!("Task {} did not complete", task_id));
+            }
+            client
+                .task
+                .get_task_by_id(task_id)
+                .map_err(|e| format!("Error getting task: {:?}", e))
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use livepeer_rs::playback::Playback;
+
+// Wall clock time of a live stream as RFC 3339, or HH:MM:SS today in the local timezone, in
+// unix milliseconds
+pub fn parse_wall_clock(time: &str) -> Result<i64, String> {
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(time.trim()) {
+        return Ok(t.timestamp_millis());
+    }
+    chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M:%S")
+        .ok()
+        .and_then(|t| {
+            chrono::Local::now()
+                .date_naive()
+                .and_time(t)
+                .and_local_timezone(chrono::Local)
+                .single()
+        })
+        .map(|t| t.timestamp_millis())
+        .ok_or(format!(
+            "Invalid time {}, expected RFC 3339 or HH:MM:SS",
+            time
+        ))
+}
+
+// Position in a recording as seconds, MM:SS or HH:MM:SS with optional fractions, in milliseconds
+pub fn parse_offset(offset: &str) -> Result<i64, String> {
+    let invalid = || {
+        format!(
+            "Invalid time {}, expected seconds, MM:SS or HH:MM:SS",
+            offset
+        )
+    };
+    let parts = offset.trim().split(':').collect::<Vec<&str>>();
+    if parts.len() > 3 {
+        return Err(invalid());
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        let value = part.parse::<f64>().map_err(|_| invalid())?;
+        if value < 0.0 {
+            return Err(invalid());
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Ok((seconds * 1000.0) as i64)
+}
+
+// Clip window of a recorded session from offsets into its recording. Studio clips recordings by
+// wall clock time, counted from the start of the session.
+pub fn recording_window(session: &serde_json::Value, start: i64, end: i64) -> (i64, i64) {
+    let started = session["createdAt"].as_i64().unwrap_or(0);
+    (started + start, started + end)
+}
+
+// Clip window ending now, for live streams
+pub fn last(seconds: f64) -> (i64, i64) {
+    let now = chrono::Utc::now().timestamp_millis();
+    (now - (seconds * 1000.0) as i64, now)
+}
+
+// Clip a live stream, or the recording of one of its sessions when `session_id` is set, between
+// two unix millisecond timestamps. Studio doesn't clip assets. Returns the clip asset and the
+// task creating it.
+pub fn create_clip(
+    playback_id: &str,
+    session_id: Option<&str>,
+    start_time: i64,
+    end_time: i64,
+    name: Option<String>,
+) -> Result<serde_json::Value, String> {
+    if end_time <= start_time {
+        return Err(String::from("The clip must end after it starts"));
+    }
+    let mut body = serde_json::json!({
+        "playbackId": playback_id,
+        "startTime": start_time,
+        "endTime": end_time,
+    });
+    if let Some(session_id) = session_id {
+        body["sessionId"] = serde_json::json!(session_id);
+    }
+    if let Some(name) = name {
+        body["name"] = serde_json::json!(name);
+    }
+    crate::api::post("/api/clip", body).map_err(|e| format!("Error creating clip: {}", e))
+}
+
+fn prompt_time(prompt: &str, parse: fn(&str) -> Result<i64, String>) -> i64 {
+    let time = dialoguer::Input::<String>::new()
+        .with_prompt(prompt)
+        .validate_with(|t: &String| parse(t).map(|_| ()))
+        .interact()
+        .unwrap();
+    parse(&time).unwrap()
+}
+
+fn prompt_name() -> Option<String> {
+    let name = dialoguer::Input::<String>::new()
+        .with_prompt("Clip name (empty for the default)")
+        .allow_empty(true)
+        .interact()
+        .unwrap();
+    Some(name).filter(|n| !n.is_empty())
+}
+
+// Create the clip, wait for it to be processed and offer to play it
+fn create_and_track(
+    client: &livepeer_rs::Livepeer,
+    playback_id: &str,
+    session_id: Option<&str>,
+    start_time: i64,
+    end_time: i64,
+    name: Option<String>,
+) {
+    let clip = match create_clip(playback_id, session_id, start_time, end_time, name) {
+        Ok(c) => c,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+    info!(
+        "Clip asset {} created, processing",
+        clip["asset"]["id"].as_str().unwrap_or("")
+    );
+    if !crate::tasks::track_task_status(clip["task"].clone(), client) {
+        error!("Clip processing failed");
+        return;
+    }
+
+    let clip_playback_id = clip["asset"]["playbackId"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+    info!("Clip ready, playback ID {}", clip_playback_id);
+    let play = dialoguer::Confirm::new()
+        .with_prompt("Play the clip?")
+        .default(true)
+        .interact()
+        .unwrap();
+    if play {
+        match client.playback.get_playback_info(&clip_playback_id) {
+            Ok(p) => crate::playback::playback(p, client),
+            Err(e) => error!("Error getting playback info: {:?}", e),
+        }
+    }
+}
+
+pub fn clip_stream(client: &livepeer_rs::Livepeer, stream: &serde_json::Value) {
+    if stream["isActive"].as_bool() != Some(true) {
+        warn!("The stream is not active, only live streams can be clipped");
+    }
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&["Last seconds of the stream", "Between two times", "< Back"])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    let (start_time, end_time) = match selection {
+        Some(0) => {
+            let seconds = dialoguer::Input::<f64>::new()
+                .with_prompt("Seconds back from the live edge")
+                .default(30.0)
+                .interact()
+                .unwrap();
+            last(seconds)
+        }
+        Some(1) => (
+            prompt_time("Start (RFC 3339 or HH:MM:SS today)", parse_wall_clock),
+            prompt_time("End (RFC 3339 or HH:MM:SS today)", parse_wall_clock),
+        ),
+        _ => return,
+    };
+    create_and_track(
+        client,
+        stream["playbackId"].as_str().unwrap_or(""),
+        None,
+        start_time,
+        end_time,
+        prompt_name(),
+    );
+}
+
+// Clip part of the recording of a session of the stream with `playback_id`
+pub fn clip_recording(
+    client: &livepeer_rs::Livepeer,
+    playback_id: &str,
+    session: &serde_json::Value,
+) {
+    if session["recordingStatus"] != "ready" {
+        warn!("The recording of this session is not ready, it can't be clipped yet");
+        return;
+    }
+    if let Some(duration) = session["sourceSegmentsDuration"].as_f64() {
+        info!("Recording duration {:.1}s", duration);
+    }
+    let start = prompt_time("Start (seconds, MM:SS or HH:MM:SS)", parse_offset);
+    let end = prompt_time("End (seconds, MM:SS or HH:MM:SS)", parse_offset);
+    let (start_time, end_time) = recording_window(session, start, end);
+    create_and_track(
+        client,
+        playback_id,
+        session["id"].as_str(),
+        start_time,
+        end_time,
+        prompt_name(),
+    );
+}
+
+// Studio's clip API takes stream and recording playback IDs only. An asset saved from a recording
+// is clipped through the session it came from, other assets can't be clipped.
+pub fn clip_asset(client: &livepeer_rs::Livepeer, asset: &serde_json::Value) {
+    let session_id = match asset["source"]["sessionId"].as_str() {
+        Some(s) if asset["source"]["type"] == "recording" => s,
+        _ => {
+            warn!("Studio can't clip assets, only live streams and stream recordings. Clip the stream, or its recording from Streams > Sessions and recordings");
+            return;
+        }
+    };
+    let recording = crate::sessions::get_session(session_id).and_then(|session| {
+        let stream = crate::api::get(&format!(
+            "/api/stream/{}",
+            session["parentId"].as_str().unwrap_or("")
+        ))
+        .map_err(|e| format!("Error getting the stream of the recording: {}", e))?;
+        Ok((stream, session))
+    });
+    match recording {
+        Ok((stream, session)) => {
+            info!(
+                "Clipping the recording of session {} this asset was saved from",
+                session_id
+            );
+            clip_recording(
+                client,
+                stream["playbackId"].as_str().unwrap_or(""),
+                &session,
+            )
+        }
+        Err(e) => error!("{}", e),
+    }
+}
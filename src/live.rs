@@ -1,7 +1,8 @@
 use colored::*;
+use serde::{Deserialize, Serialize};
 use livepeer_rs::vod::{Task, Vod};
 
-const REGIONS: &'static [&'static str] = &["fra", "prg", "nyc", "lon", "lax", "mdw", "sin", "sao"];
+pub const REGIONS: &'static [&'static str] = &["fra", "prg", "nyc", "lon", "lax", "mdw", "sin", "sao"];
 
 pub fn streams(client: &livepeer_rs::Livepeer) -> bool {
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
@@ -11,6 +12,9 @@ pub fn streams(client: &livepeer_rs::Livepeer) -> bool {
             "Get Stream By ID",
             "Get Stream by Playback ID",
             "Create Stream",
+            "Profile Presets",
+            "Rotate All Stream Keys",
+            "Running Pushes",
             "< Back",
         ])
         .default(0)
@@ -93,32 +97,30 @@ pub fn streams(client: &livepeer_rs::Livepeer) -> bool {
             }
 
             if index == 4 {
-                // Create stream
-                let name = dialoguer::Input::<String>::new()
-                    .with_prompt("Enter stream name")
-                    .interact()
-                    .unwrap();
+                create_stream_wizard(client);
+                streams(client);
+                std::process::exit(0);
+            }
 
-                let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
-
-                client.stream.clone().create_stream(
-                    &name,
-                    &vec![livepeer_rs::data::stream::Profile {
-                        bitrate: 250000,
-                        fps: 0,
-                        height: 240,
-                        name: String::from("240p0"),
-                        width: 426,
-                        gop: None,
-                    }],
-                    playback_policy,
-                );
+            if index == 5 {
+                crate::presets::presets();
+                streams(client);
+                std::process::exit(0);
+            }
 
+            if index == 6 {
+                rotate_all_stream_keys(client);
                 streams(client);
                 std::process::exit(0);
             }
 
-            if index == 4 {
+            if index == 7 {
+                crate::pushes::pushes(None);
+                streams(client);
+                std::process::exit(0);
+            }
+
+            if index == 8 {
                 crate::list_options(&client);
                 std::process::exit(0);
             }
@@ -154,7 +156,7 @@ pub fn streams(client: &livepeer_rs::Livepeer) -> bool {
                                 format!(
                                     "{} - {:?} - {} - {:?} - {}",
                                     x.id,
-                                    x.stream_key.clone(),
+                                    x.stream_key.as_deref().map(redact_key),
                                     x.name,
                                     x.playback_id.clone(),
                                     x.is_active
@@ -208,6 +210,410 @@ pub fn streams(client: &livepeer_rs::Livepeer) -> bool {
     return false;
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct StreamProfile {
+    pub name: String,
+    pub width: u64,
+    pub height: u64,
+    // Bits per second
+    pub bitrate: u64,
+    // 0 keeps the frame rate of the source
+    pub fps: u64,
+    // Seconds between keyframes, or "intra" for keyframes only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gop: Option<String>,
+}
+
+impl StreamProfile {
+    pub fn new(height: u64, width: u64, bitrate: u64, fps: u64, gop: Option<String>) -> Self {
+        StreamProfile {
+            name: format!("{}p{}", height, fps),
+            width,
+            height,
+            bitrate,
+            fps,
+            gop,
+        }
+    }
+}
+
+pub fn default_profiles() -> Vec<StreamProfile> {
+    vec![StreamProfile::new(240, 426, 250000, 0, None)]
+}
+
+// Bits per second, with an optional k or M suffix
+pub fn parse_bitrate(bitrate: &str) -> Result<u64, String> {
+    let bitrate = bitrate.trim();
+    let (number, multiplier) = match bitrate.chars().last() {
+        Some('k') | Some('K') => (&bitrate[..bitrate.len() - 1], 1000.0),
+        Some('m') | Some('M') => (&bitrate[..bitrate.len() - 1], 1000000.0),
+        _ => (bitrate, 1.0),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| *n > 0.0)
+        .map(|n| (n * multiplier) as u64)
+        .ok_or(format!("Invalid bitrate {}", bitrate))
+}
+
+// name:WIDTHxHEIGHT:bitrate[:fps[:gop]], e.g. 720p:1280x720:3M:30:2
+pub fn parse_profile(profile: &str) -> Result<StreamProfile, String> {
+    let parts = profile.split(':').collect::<Vec<&str>>();
+    if parts.len() < 3 || parts.len() > 5 {
+        return Err(format!(
+            "Invalid profile {}, expected name:WIDTHxHEIGHT:bitrate[:fps[:gop]]",
+            profile
+        ));
+    }
+    let (width, height) = parts[1]
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u64>().ok()?, h.parse::<u64>().ok()?)))
+        .ok_or(format!("Invalid resolution {} in profile {}", parts[1], profile))?;
+    let fps = match parts.get(3) {
+        Some(f) => f
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid fps {} in profile {}", f, profile))?,
+        None => 0,
+    };
+    Ok(StreamProfile {
+        name: parts[0].to_string(),
+        width,
+        height,
+        bitrate: parse_bitrate(parts[2])?,
+        fps,
+        gop: parts.get(4).map(|g| g.to_string()),
+    })
+}
+
+fn prompt_profile() -> StreamProfile {
+    let height = dialoguer::Input::<u64>::new()
+        .with_prompt("Height")
+        .default(720)
+        .interact()
+        .unwrap();
+    // Default to 16:9, rounded to an even width
+    let width = dialoguer::Input::<u64>::new()
+        .with_prompt("Width")
+        .default((height * 16 / 9 + 1) / 2 * 2)
+        .interact()
+        .unwrap();
+    let bitrate = dialoguer::Input::<String>::new()
+        .with_prompt("Bitrate (bits/s, k and M suffixes allowed)")
+        .default(String::from("3M"))
+        .validate_with(|b: &String| parse_bitrate(b).map(|_| ()))
+        .interact()
+        .unwrap();
+    let fps = dialoguer::Input::<u64>::new()
+        .with_prompt("Frame rate (0 keeps the source frame rate)")
+        .default(0)
+        .interact()
+        .unwrap();
+    let gop = dialoguer::Input::<String>::new()
+        .with_prompt("Seconds between keyframes (empty for the default)")
+        .allow_empty(true)
+        .interact()
+        .unwrap();
+    let mut profile = StreamProfile::new(
+        height,
+        width,
+        parse_bitrate(&bitrate).unwrap(),
+        fps,
+        Some(gop).filter(|g| !g.is_empty()),
+    );
+    profile.name = dialoguer::Input::<String>::new()
+        .with_prompt("Profile name")
+        .default(profile.name)
+        .interact()
+        .unwrap();
+    profile
+}
+
+pub fn print_profiles(profiles: &Vec<StreamProfile>) {
+    for p in profiles {
+        println!(
+            "  {:<10} {}x{} {} kbps, fps {}, gop {}",
+            p.name.bold(),
+            p.width,
+            p.height,
+            p.bitrate / 1000,
+            if p.fps == 0 { String::from("source") } else { p.fps.to_string() },
+            p.gop.clone().unwrap_or(String::from("default"))
+        );
+    }
+}
+
+// Edit a ladder until it has at least one profile
+pub fn prompt_profiles(mut profiles: Vec<StreamProfile>) -> Vec<StreamProfile> {
+    loop {
+        if !profiles.is_empty() {
+            print_profiles(&profiles);
+        }
+        let items = ["Add a profile", "Remove a profile", "Done"];
+        let action = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .items(&items)
+            .default(if profiles.is_empty() { 0 } else { 2 })
+            .interact_on_opt(&crate::Term::stderr())
+            .unwrap();
+        match action {
+            Some(0) => profiles.push(prompt_profile()),
+            Some(1) if !profiles.is_empty() => {
+                let names = profiles.iter().map(|p| p.name.clone()).collect::<Vec<_>>();
+                if let Some(i) =
+                    dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                        .items(&names)
+                        .default(0)
+                        .interact_on_opt(&crate::Term::stderr())
+                        .unwrap()
+                {
+                    profiles.remove(i);
+                }
+            }
+            Some(2) | None if !profiles.is_empty() => return profiles,
+            _ => warn!("Add at least one profile"),
+        }
+    }
+}
+
+pub struct NewStream {
+    pub name: String,
+    pub profiles: Vec<StreamProfile>,
+    pub record: bool,
+    pub creator_id: Option<String>,
+    pub user_tags: serde_json::Map<String, serde_json::Value>,
+    pub playback_policy: Option<serde_json::Value>,
+}
+
+pub fn create_stream(new_stream: &NewStream) -> Result<serde_json::Value, String> {
+    let mut body = serde_json::json!({
+        "name": new_stream.name,
+        "profiles": new_stream.profiles,
+        "record": new_stream.record,
+    });
+    if let Some(creator_id) = &new_stream.creator_id {
+        body["creatorId"] = serde_json::json!({ "type": "unverified", "value": creator_id });
+    }
+    if !new_stream.user_tags.is_empty() {
+        body["userTags"] = serde_json::Value::Object(new_stream.user_tags.clone());
+    }
+    if let Some(policy) = &new_stream.playback_policy {
+        body["playbackPolicy"] = policy.clone();
+    }
+    crate::api::post("/api/stream", body).map_err(|e| format!("Error creating stream: {}", e))
+}
+
+// The fields needed to start streaming, with the ingest and playback urls of the env
+pub fn stream_summary(stream: &serde_json::Value) -> serde_json::Value {
+    let endpoints = crate::api::endpoints();
+    let playback_id = stream["playbackId"].as_str().unwrap_or("");
+    serde_json::json!({
+        "id": stream["id"],
+        "name": stream["name"],
+        "streamKey": stream["streamKey"],
+        "playbackId": stream["playbackId"],
+        "record": stream["record"],
+        "playbackPolicy": stream["playbackPolicy"],
+        "profiles": stream["profiles"],
+        "ingest": ingest_urls(stream["streamKey"].as_str().unwrap_or("")),
+        "playback": {
+            "hls": format!("{}/{}/index.m3u8", endpoints.hls, playback_id),
+            "player": format!("{}?v={}", endpoints.player, playback_id),
+        },
+    })
+}
+
+pub fn ingest_urls(stream_key: &str) -> serde_json::Value {
+    let endpoints = crate::api::endpoints();
+    serde_json::json!({
+        "rtmp": format!("{}/{}", endpoints.rtmp, stream_key),
+        "srt": format!("{}?streamid={}", endpoints.srt, stream_key),
+        "webrtc": format!("{}/{}", endpoints.webrtc, stream_key),
+    })
+}
+
+// Only the start of a key, so lists can be shown or shared without leaking it
+pub fn redact_key(stream_key: &str) -> String {
+    format!("{}*****", stream_key.chars().take(4).collect::<String>())
+}
+
+// Studio generates stream keys and its stream PATCH doesn't take one, so a key is rotated by
+// creating a replacement stream with the same settings and deleting the old one. The replacement
+// has a new id and playback id.
+pub fn rotate_stream_key(id: &str) -> Result<serde_json::Value, String> {
+    let stream = crate::api::get(&format!("/api/stream/{}", id))
+        .map_err(|e| format!("Error getting stream: {}", e))?;
+    let profiles: Vec<StreamProfile> =
+        serde_json::from_value(stream["profiles"].clone()).unwrap_or_default();
+    let playback_policy = match &stream["playbackPolicy"] {
+        serde_json::Value::Null => None,
+        policy => Some(policy.clone()),
+    };
+    let replacement = create_stream(&NewStream {
+        name: stream["name"].as_str().unwrap_or(id).to_string(),
+        profiles,
+        record: stream["record"].as_bool().unwrap_or(false),
+        creator_id: stream["creatorId"]["value"].as_str().map(String::from),
+        user_tags: stream["userTags"].as_object().cloned().unwrap_or_default(),
+        playback_policy,
+    })?;
+    let new_id = replacement["id"].as_str().unwrap_or("").to_string();
+    if stream["multistream"]["targets"]
+        .as_array()
+        .map_or(false, |targets| !targets.is_empty())
+    {
+        if let Err(e) = update_stream(
+            &new_id,
+            serde_json::json!({ "multistream": stream["multistream"] }),
+        ) {
+            warn!("{}, attach the multistream targets again", e);
+        }
+    }
+    // Keep the old stream if it can't be deleted, its key would otherwise still work unnoticed
+    delete_stream(id).map_err(|e| {
+        format!(
+            "{}. The replacement stream {} was created, delete {} by hand",
+            e, new_id, id
+        )
+    })?;
+    let mut rotated = stream_summary(&replacement);
+    rotated["previousId"] = serde_json::json!(id);
+    rotated["previousPlaybackId"] = stream["playbackId"].clone();
+    Ok(rotated)
+}
+
+// Rotate the key of every stream of a user, carrying on past failures
+pub fn rotate_stream_keys(
+    client: &livepeer_rs::Livepeer,
+    user_id: String,
+) -> Result<Vec<serde_json::Value>, String> {
+    let streams = client
+        .stream
+        .clone()
+        .get_streams_by_user_id(user_id)
+        .map_err(|e| format!("Error getting streams: {:?}", e))?;
+    Ok(streams
+        .iter()
+        .map(|s| match rotate_stream_key(&s.id) {
+            Ok(mut rotated) => {
+                info!("Rotated the key of {}", s.name);
+                rotated["status"] = serde_json::json!("rotated");
+                rotated
+            }
+            Err(e) => {
+                error!("{}", e);
+                serde_json::json!({ "id": s.id, "name": s.name, "status": "failed", "error": e })
+            }
+        })
+        .collect())
+}
+
+fn print_rotated_key(rotated: &serde_json::Value) {
+    let field = |v: &serde_json::Value| v.as_str().map_or(v.to_string(), String::from);
+    println!("{}", field(&rotated["name"]).bold());
+    if let Some(error) = rotated["error"].as_str() {
+        println!("  {}", error.red());
+        return;
+    }
+    println!(
+        "  ID:          {} (was {})",
+        field(&rotated["id"]),
+        field(&rotated["previousId"])
+    );
+    println!(
+        "  Playback ID: {} (was {})",
+        field(&rotated["playbackId"]),
+        field(&rotated["previousPlaybackId"])
+    );
+    println!("  Stream key:  {}", field(&rotated["streamKey"]).yellow());
+    println!("  RTMP:        {}", field(&rotated["ingest"]["rtmp"]));
+    println!("  SRT:         {}", field(&rotated["ingest"]["srt"]));
+}
+
+fn rotate_all_stream_keys(client: &livepeer_rs::Livepeer) {
+    let user_id = dialoguer::Input::<String>::new()
+        .with_prompt("Rotate the keys of the streams of user ID")
+        .default(client.user.user_id.clone())
+        .interact()
+        .unwrap();
+    if !confirm(format!(
+        "Rotate the key of every stream of {}? Each stream is replaced by a new one with a new ID and playback ID, encoders using the old keys will be disconnected",
+        user_id
+    )) {
+        return;
+    }
+    match rotate_stream_keys(client, user_id) {
+        Ok(rotated) => rotated.iter().for_each(print_rotated_key),
+        Err(e) => error!("{}", e),
+    }
+}
+
+fn print_stream_summary(summary: &serde_json::Value) {
+    let field = |v: &serde_json::Value| v.as_str().map_or(v.to_string(), String::from);
+    println!("{}", "Stream created".green().bold());
+    println!("  ID:          {}", field(&summary["id"]));
+    println!("  Name:        {}", field(&summary["name"]));
+    println!("  Stream key:  {}", field(&summary["streamKey"]).yellow());
+    println!("  Playback ID: {}", field(&summary["playbackId"]));
+    println!("  Recording:   {}", summary["record"].as_bool().unwrap_or(false));
+    println!("  RTMP:        {}", field(&summary["ingest"]["rtmp"]));
+    println!("  SRT:         {}", field(&summary["ingest"]["srt"]));
+    println!("  WebRTC:      {}", field(&summary["ingest"]["webrtc"]));
+    println!("  HLS:         {}", field(&summary["playback"]["hls"]));
+    println!("  Player:      {}", field(&summary["playback"]["player"]));
+}
+
+fn create_stream_wizard(client: &livepeer_rs::Livepeer) {
+    let name = dialoguer::Input::<String>::new()
+        .with_prompt("Enter stream name")
+        .interact()
+        .unwrap();
+    let profiles = crate::presets::select_profiles();
+    let record = dialoguer::Confirm::new()
+        .with_prompt("Record the stream?")
+        .default(false)
+        .interact()
+        .unwrap();
+    let creator_id = dialoguer::Input::<String>::new()
+        .with_prompt("Creator ID (empty for none)")
+        .allow_empty(true)
+        .interact()
+        .unwrap();
+    let tags = dialoguer::Input::<String>::new()
+        .with_prompt("Tags as key=value separated by commas (empty for none)")
+        .allow_empty(true)
+        .validate_with(|t: &String| parse_tags(t).map(|_| ()))
+        .interact()
+        .unwrap();
+    let playback_policy = crate::accesscontrol::generate_playback_policy(&client);
+
+    let new_stream = NewStream {
+        name,
+        profiles,
+        record,
+        creator_id: Some(creator_id).filter(|c| !c.is_empty()),
+        user_tags: parse_tags(&tags).unwrap(),
+        playback_policy,
+    };
+    match create_stream(&new_stream) {
+        Ok(stream) => print_stream_summary(&stream_summary(&stream)),
+        Err(e) => error!("{}", e),
+    }
+}
+
+pub fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    tag.split_once('=')
+        .filter(|(k, _)| !k.trim().is_empty())
+        .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+        .ok_or(format!("Invalid tag {}, expected key=value", tag))
+}
+
+fn parse_tags(tags: &str) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    tags.split(',')
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| parse_tag(t).map(|(k, v)| (k, serde_json::Value::from(v))))
+        .collect()
+}
+
 pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::Livepeer) {
     let a = stream.unwrap();
     let task = client
@@ -217,6 +623,17 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
     let pretty_asset = serde_json::to_string_pretty(&a).unwrap();
     println!("{}", pretty_asset);
 
+    let record_label = if a["record"].as_bool() == Some(true) {
+        "Disable recording"
+    } else {
+        "Enable recording"
+    };
+    let suspend_label = if a["suspended"].as_bool() == Some(true) {
+        "Unsuspend"
+    } else {
+        "Suspend"
+    };
+
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
         .items(&[
             "< Back",
@@ -225,10 +642,19 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
             "Change policy",
             "Push",
             "Test Push into Region",
-            "Get running push",
+            "Running pushes",
             "Test on all regions",
             "Open with lvpr.tv (WebRtc)",
             "Open with lvpr.tv (HLS)",
+            "Rename",
+            record_label,
+            suspend_label,
+            "Terminate active session",
+            "Delete",
+            "Rotate stream key",
+            "Multistream targets",
+            "Sessions and recordings",
+            "Create clip",
         ])
         .default(0)
         .interact_on_opt(&crate::Term::stderr())
@@ -269,41 +695,32 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
             }
 
             if index == 3 {
-                // print todo
-                info!("TODO Change policy");
-                streams(client);
+                let updated = crate::accesscontrol::change_policy(client, "stream", &a);
+                inspect_stream(Some(updated.unwrap_or(a.clone())), client);
             }
 
             if index == 4 {
-                let ffmpeg_path = get_ffmpeg_path();
-                let stream_client = client.clone();
-
-                let current_folder_string = std::env::current_dir()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-
-                let files =
-                    crate::assets::upload::list_files_and_folders(&current_folder_string, None);
-
-                let file_to_push = get_file_to_push(&current_folder_string);
-                let stream = a.clone();
-                if let Ok(ffp) = ffmpeg_path {
-                    //std::thread::spawn(move || {
-                    stream_client.rtmp.push_to_region(
-                        &stream["streamKey"].to_string().replace("\"", ""),
-                        &file_to_push.to_string(),
-                        &String::from(REGIONS[0]),
-                        &ffp,
-                        &mut Some(stream["playbackId"].to_string().replace('"', "")),
-                    );
-                    //});
-                    streams(client);
-                } else {
-                    error!("FFMPEG not found");
-                    streams(client);
+                let (source, input_args) = match select_push_source(true) {
+                    Some(PushSource::File(file)) => {
+                        let looped = dialoguer::Confirm::new()
+                            .with_prompt("Loop the file until the push is stopped?")
+                            .default(false)
+                            .interact()
+                            .unwrap();
+                        let input_args = crate::pushes::file_input(&file, looped);
+                        (file, input_args)
+                    }
+                    Some(PushSource::TestSource(test_source)) => {
+                        (test_source.label(), test_source.input_args())
+                    }
+                    None => return inspect_stream(Some(a.clone()), client),
+                };
+                let region = select_region();
+                let push = crate::pushes::start(&a, &region, &source, input_args);
+                if let Err(e) = push {
+                    error!("{}", e);
                 }
+                inspect_stream(Some(a.clone()), client);
             }
 
             if index == 5 {
@@ -323,25 +740,19 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
                 let ffmpeg_path = get_ffmpeg_path();
                 let stream_client = client.clone();
 
-                let current_folder_string = std::env::current_dir()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-
-                let files =
-                    crate::assets::upload::list_files_and_folders(&current_folder_string, None);
-
-                let file_to_push = get_file_to_push(&current_folder_string);
+                let push_source = match select_push_source(false) {
+                    Some(p) => p,
+                    None => return inspect_stream(Some(a.clone()), client),
+                };
                 let stream = a.clone();
 
                 if let Ok(ffp) = ffmpeg_path.clone() {
-                    let push = stream_client.rtmp.push_to_region(
-                        &stream["streamKey"].to_string().replace("\"", ""),
-                        &file_to_push.to_string(),
-                        &region_selected.to_string(),
+                    let push = test_push(
+                        &stream_client,
+                        &stream,
+                        &push_source,
+                        &region_selected,
                         &ffp,
-                        &mut Some(stream["playbackId"].to_string().replace('"', "")),
                     );
 
                     if let Ok(e) = push {
@@ -362,35 +773,28 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
             }
 
             if index == 6 {
-                // print todo
-                find_threads(&a["playbackId"].to_string().replace('"', ""));
-                streams(client);
+                crate::pushes::pushes(a["id"].as_str());
+                inspect_stream(Some(a.clone()), client);
             }
 
             if index == 7 {
                 let ffmpeg_path = get_ffmpeg_path();
                 let stream_client = client.clone();
 
-                let current_folder_string = std::env::current_dir()
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-
-                let files =
-                    crate::assets::upload::list_files_and_folders(&current_folder_string, None);
-
-                let file_to_push = get_file_to_push(&current_folder_string);
+                let push_source = match select_push_source(false) {
+                    Some(p) => p,
+                    None => return inspect_stream(Some(a.clone()), client),
+                };
                 let stream = a.clone();
                 for region in REGIONS {
                     info!("Testing region: {}", region);
                     if let Ok(ffp) = ffmpeg_path.clone() {
-                        let push = stream_client.rtmp.push_to_region(
-                            &stream["streamKey"].to_string().replace("\"", ""),
-                            &file_to_push.to_string(),
+                        let push = test_push(
+                            &stream_client,
+                            &stream,
+                            &push_source,
                             &region.to_string(),
                             &ffp,
-                            &mut Some(stream["playbackId"].to_string().replace('"', "")),
                         );
 
                         if let Ok(e) = push {
@@ -422,6 +826,56 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
                 let url = format!("https://lvpr.tv?v={}&lowLatency=false", playback_id);
                 let _ = open::that(&url);
             }
+
+            if index == 18 {
+                crate::clips::clip_stream(client, &a);
+                inspect_stream(Some(a.clone()), client);
+            }
+
+            if index == 17 {
+                crate::sessions::sessions(client, &a);
+                inspect_stream(Some(a.clone()), client);
+            }
+
+            if index == 16 {
+                let updated = crate::multistream::multistream(&a);
+                inspect_stream(Some(updated.unwrap_or(a.clone())), client);
+            }
+
+            if index == 15 {
+                let name = a["name"].as_str().unwrap_or("");
+                if !confirm(format!(
+                    "Rotate the key of {}? It is replaced by a new stream with a new ID and playback ID, encoders using the old key will be disconnected",
+                    name
+                )) {
+                    inspect_stream(Some(a.clone()), client);
+                    return;
+                }
+                match rotate_stream_key(a["id"].as_str().unwrap_or("")) {
+                    Ok(rotated) => {
+                        print_rotated_key(&rotated);
+                        let replacement = crate::api::get(&format!(
+                            "/api/stream/{}",
+                            rotated["id"].as_str().unwrap_or("")
+                        ));
+                        inspect_stream(Some(replacement.unwrap_or(a.clone())), client);
+                    }
+                    Err(e) => {
+                        error!("{}", e);
+                        inspect_stream(Some(a.clone()), client);
+                    }
+                }
+            }
+
+            if (10..=14).contains(&index) {
+                match manage_stream(index, &a) {
+                    Some(updated) => inspect_stream(Some(updated), client),
+                    None if index == 14 => {
+                        streams(client);
+                    }
+                    None => inspect_stream(Some(a.clone()), client),
+                }
+            }
         }
         None => {
             error!("No selection made");
@@ -429,7 +883,254 @@ pub fn inspect_stream(stream: Option<serde_json::Value>, client: &livepeer_rs::L
     }
 }
 
-pub fn get_file_to_push(current_folder_string: &String) -> String {
+fn confirm(prompt: String) -> bool {
+    dialoguer::Confirm::new()
+        .with_prompt(prompt)
+        .default(false)
+        .interact()
+        .unwrap()
+}
+
+// Update fields of a stream (name, record, suspended...) and return it refreshed
+pub fn update_stream(id: &str, fields: serde_json::Value) -> Result<serde_json::Value, String> {
+    crate::api::patch(&format!("/api/stream/{}", id), fields)
+        .map_err(|e| format!("Error updating stream: {}", e))?;
+    crate::api::get(&format!("/api/stream/{}", id))
+}
+
+// Stop the active session of a stream, the stream can be pushed to again
+pub fn terminate_stream(id: &str) -> Result<(), String> {
+    crate::api::delete(&format!("/api/stream/{}/terminate", id))
+        .map(|_| ())
+        .map_err(|e| format!("Error terminating stream: {}", e))
+}
+
+pub fn delete_stream(id: &str) -> Result<(), String> {
+    crate::api::delete(&format!("/api/stream/{}", id))
+        .map(|_| ())
+        .map_err(|e| format!("Error deleting stream: {}", e))
+}
+
+// Rename, toggle recording, (un)suspend, terminate or delete a stream after a confirmation.
+// Returns the refreshed stream, or None when nothing changed or the stream was deleted.
+fn manage_stream(index: usize, a: &serde_json::Value) -> Option<serde_json::Value> {
+    let id = a["id"].as_str().unwrap_or("");
+    let name = a["name"].as_str().unwrap_or("");
+
+    let result = match index {
+        10 => {
+            let new_name = dialoguer::Input::<String>::new()
+                .with_prompt("New name")
+                .default(name.to_string())
+                .interact()
+                .unwrap();
+            if new_name == name || !confirm(format!("Rename {} to {}?", name, new_name)) {
+                return None;
+            }
+            update_stream(id, serde_json::json!({ "name": new_name }))
+        }
+        11 => {
+            let record = a["record"].as_bool() != Some(true);
+            let action = if record { "Enable" } else { "Disable" };
+            if !confirm(format!("{} recording of {}?", action, name)) {
+                return None;
+            }
+            update_stream(id, serde_json::json!({ "record": record }))
+        }
+        12 => {
+            let suspended = a["suspended"].as_bool() != Some(true);
+            let action = if suspended {
+                "Suspend (active sessions are stopped and new pushes rejected)"
+            } else {
+                "Unsuspend"
+            };
+            if !confirm(format!("{} {}?", action, name)) {
+                return None;
+            }
+            update_stream(id, serde_json::json!({ "suspended": suspended }))
+        }
+        13 => {
+            if a["isActive"].as_bool() == Some(false) {
+                warn!("{} is not active", name);
+            }
+            if !confirm(format!("Terminate the active session of {}?", name)) {
+                return None;
+            }
+            terminate_stream(id).and_then(|_| crate::api::get(&format!("/api/stream/{}", id)))
+        }
+        14 => {
+            if !confirm(format!("Delete {} ({})? This can't be undone", name, id)) {
+                return None;
+            }
+            match delete_stream(id) {
+                Ok(_) => info!("Stream {} deleted", id),
+                Err(e) => error!("{}", e),
+            }
+            return None;
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(updated) => {
+            info!("Stream {} updated", id);
+            Some(updated)
+        }
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+}
+
+// Push a file to a region, paced by ffmpeg with capped bitrate when a bandwidth limit is set
+pub fn push_to_region(
+    client: &livepeer_rs::Livepeer,
+    stream_key: &String,
+    file: &String,
+    region: &String,
+    ffmpeg: &String,
+    playback_id: &mut Option<String>,
+) -> Result<(), String> {
+    let limit = match crate::ratelimit::limit() {
+        Some(l) => l,
+        None => {
+            return client
+                .rtmp
+                .push_to_region(stream_key, file, region, ffmpeg, playback_id)
+                .map_err(|e| format!("{:?}", e))
+        }
+    };
+
+    info!("Pushing {} to {} at most {} bytes/s", file, region, limit);
+    push_input_to_region(stream_key, crate::pushes::file_input(file, false), region, ffmpeg)
+}
+
+// Push ffmpeg input (a file or a generated source) to a region and wait for ffmpeg to exit
+pub fn push_input_to_region(
+    stream_key: &String,
+    input_args: Vec<String>,
+    region: &String,
+    ffmpeg: &String,
+) -> Result<(), String> {
+    let url = format!("{}/{}", crate::api::regional_rtmp(region), stream_key);
+    let output = std::process::Command::new(ffmpeg)
+        .args(["-v", "error"])
+        .args(&input_args)
+        .args(crate::pushes::encoding_args())
+        .args(["-f", "flv", &url])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub enum PushSource {
+    File(String),
+    TestSource(crate::pushes::TestSource),
+}
+
+// Ask for a test pattern resolution, fps and duration. Tests need a duration to end.
+fn prompt_test_source(until_stopped: bool) -> crate::pushes::TestSource {
+    let sizes = ["1920x1080", "1280x720", "854x480", "640x360"];
+    let size = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Resolution")
+        .items(&sizes)
+        .default(1)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap()
+        .unwrap_or(1);
+    let (width, height) = crate::pushes::parse_size(sizes[size]).unwrap();
+    let fps = dialoguer::Input::<u32>::new()
+        .with_prompt("Frame rate")
+        .default(30)
+        .validate_with(|f: &u32| if *f > 0 { Ok(()) } else { Err("Frame rate must be positive") })
+        .interact()
+        .unwrap();
+    let duration = dialoguer::Input::<u64>::new()
+        .with_prompt(if until_stopped {
+            "Duration in seconds (0 to push until stopped)"
+        } else {
+            "Duration in seconds"
+        })
+        .default(if until_stopped { 0 } else { 30 })
+        .validate_with(|d: &u64| {
+            if until_stopped || *d > 0 {
+                Ok(())
+            } else {
+                Err("Duration must be positive")
+            }
+        })
+        .interact()
+        .unwrap();
+    crate::pushes::TestSource {
+        width,
+        height,
+        fps,
+        duration,
+    }
+}
+
+// Pick a local file or a generated test pattern to push
+fn select_push_source(until_stopped: bool) -> Option<PushSource> {
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Source")
+        .items(&["File", "Test pattern (no file needed)"])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap()?;
+    if selection == 1 {
+        return Some(PushSource::TestSource(prompt_test_source(until_stopped)));
+    }
+    let current_folder_string = std::env::current_dir()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    get_file_to_push(&current_folder_string).map(PushSource::File)
+}
+
+// Blocking push of a source, used by the region tests
+fn test_push(
+    client: &livepeer_rs::Livepeer,
+    stream: &serde_json::Value,
+    source: &PushSource,
+    region: &String,
+    ffmpeg: &String,
+) -> Result<(), String> {
+    let stream_key = stream["streamKey"].as_str().unwrap_or("").to_string();
+    match source {
+        PushSource::File(file) => push_to_region(
+            client,
+            &stream_key,
+            file,
+            region,
+            ffmpeg,
+            &mut stream["playbackId"].as_str().map(String::from),
+        ),
+        PushSource::TestSource(test_source) => {
+            info!("Pushing {} to {}", test_source.label(), region);
+            push_input_to_region(&stream_key, test_source.input_args(), region, ffmpeg)
+        }
+    }
+}
+
+fn select_region() -> String {
+    let index = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Region")
+        .items(&REGIONS)
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+    REGIONS[index.unwrap_or(0)].to_string()
+}
+
+pub fn get_file_to_push(current_folder_string: &String) -> Option<String> {
     let files = crate::assets::upload::list_files_and_folders(&current_folder_string, None);
     let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
         .items(&files)
@@ -462,13 +1163,13 @@ pub fn get_file_to_push(current_folder_string: &String) -> String {
                         .to_str()
                         .unwrap()
                         .to_string();
-                    return path_of_file.to_string();
+                    return Some(path_of_file.to_string());
                 }
             }
         }
         None => {
-            error!("No selection made, going back");
-            return String::from("/tmp/video.mp4");
+            warn!("No file selected");
+            return None;
         }
     }
 }
@@ -545,19 +1246,37 @@ pub fn get_ffplay_path() -> Result<String, String> {
     }
 }
 
-pub fn find_threads(thread_id: &str) -> String {
-    // ps awxx | grep "d7ea1fe6"
-    let mut cmd = std::process::Command::new("ps");
-    cmd.arg("awxx");
-    cmd.arg("|");
-    cmd.arg("grep");
-    cmd.arg(format!("{}", thread_id));
-
-    println!("running command {:?}", cmd);
+pub fn get_ffprobe_path() -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let ffprobe_path = which::which("ffprobe");
+        if ffprobe_path.is_ok() {
+            let path = ffprobe_path.unwrap().to_str().unwrap().to_string();
+            return Ok(format!("{}", path));
+        } else {
+            return Err("No ffprobe in path".to_string());
+        }
+    }
 
-    let output = cmd.output().expect("failed to execute process");
+    #[cfg(target_os = "linux")]
+    {
+        let ffprobe_path = which::which("ffprobe");
+        if ffprobe_path.is_ok() {
+            let path = ffprobe_path.unwrap().to_str().unwrap().to_string();
+            return Ok(format!("{}", path));
+        } else {
+            return Err("No ffprobe in path".to_string());
+        }
+    }
 
-    let output_string = String::from_utf8_lossy(&output.stdout);
-    println!("output thread search: {}", output_string);
-    return output_string.to_string();
+    #[cfg(target_os = "macos")]
+    {
+        let ffprobe_path = which::which("ffprobe");
+        if ffprobe_path.is_ok() {
+            let path = ffprobe_path.unwrap().to_str().unwrap().to_string();
+            return Ok(format!("{}", path));
+        } else {
+            return Err("No ffprobe in path".to_string());
+        }
+    }
 }
@@ -9,10 +9,20 @@ use colored::*;
 use console::Term;
 
 pub mod accesscontrol;
+pub mod api;
 pub mod assets;
 pub mod auth;
+pub mod cli;
+pub mod clips;
 pub mod live;
+pub mod multistream;
+pub mod output;
 pub mod playback;
+pub mod presets;
+pub mod process;
+pub mod pushes;
+pub mod ratelimit;
+pub mod sessions;
 pub mod tasks;
 pub mod users;
 pub mod ai;
@@ -21,6 +31,13 @@ use env_logger::{filter::Filter, fmt::Color, Builder, Logger};
 
 fn main() {
     env_logger::init_from_env(env_logger::Env::default().filter_or("LIVEPEER_STUDIO_LOG", "warn"));
+
+    let args = <cli::Cli as clap::Parser>::parse();
+    ratelimit::set_limit(args.limit_rate);
+    if let Some(command) = args.command {
+        std::process::exit(cli::run(command, &args.env, args.api_key, args.output));
+    }
+
     println!(
         "{}",
         r#"
@@ -48,13 +65,7 @@ pub fn init() {
         .unwrap();
 
     let lenv = match selection {
-        Some(index) => match index {
-            0 => "prod",
-            1 => "stg",
-            2 => "dev",
-            3 => "box",
-            _ => "stg",
-        },
+        Some(index) => api::ENVS[index],
         None => "stg",
     };
 
@@ -65,15 +76,11 @@ pub fn init() {
     });
 
     // Initialize livepeer client
-    let _lvpr_env = match lenv {
-        "prod" => livepeer_rs::LivepeerEnv::Prod,
-        "stg" => livepeer_rs::LivepeerEnv::Stg,
-        "dev" => livepeer_rs::LivepeerEnv::Dev,
-        "box" => livepeer_rs::LivepeerEnv::Box,
-        _ => livepeer_rs::LivepeerEnv::Stg,
-    };
+    let _lvpr_env = api::lvpr_env(lenv);
 
     info!("Initializing livepeer client on env {}", lenv);
+    auth::set_current_env(lenv);
+    auth::set_current_api_key(&api_key);
 
     let lvpr_client = livepeer_rs::Livepeer::new(Some(api_key), Some(_lvpr_env)).unwrap();
 
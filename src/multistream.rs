@@ -0,0 +1,612 @@
+use colored::*;
+
+// Profile of a stream target meaning the source rendition is restreamed
+pub const SOURCE_PROFILE: &str = "source";
+
+fn validate_url(url: &str) -> Result<(), String> {
+    if ["rtmp://", "rtmps://", "srt://"]
+        .iter()
+        .any(|s| url.starts_with(s))
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid ingest url {}, expected rtmp://, rtmps:// or srt://",
+            url
+        ))
+    }
+}
+
+pub fn list_targets() -> Result<serde_json::Value, String> {
+    crate::api::get("/api/multistream/target")
+        .map_err(|e| format!("Error getting multistream targets: {}", e))
+}
+
+pub fn create_target(name: &str, url: &str) -> Result<serde_json::Value, String> {
+    validate_url(url)?;
+    crate::api::post(
+        "/api/multistream/target",
+        serde_json::json!({ "name": name, "url": url }),
+    )
+    .map_err(|e| format!("Error creating multistream target: {}", e))
+}
+
+// Update the name, url or disabled flag of a target and return it refreshed
+pub fn update_target(id: &str, fields: serde_json::Value) -> Result<serde_json::Value, String> {
+    if let Some(url) = fields["url"].as_str() {
+        validate_url(url)?;
+    }
+    crate::api::patch(&format!("/api/multistream/target/{}", id), fields)
+        .map_err(|e| format!("Error updating multistream target: {}", e))?;
+    crate::api::get(&format!("/api/multistream/target/{}", id))
+}
+
+pub fn delete_target(id: &str) -> Result<(), String> {
+    crate::api::delete(&format!("/api/multistream/target/{}", id))
+        .map(|_| ())
+        .map_err(|e| format!("Error deleting multistream target: {}", e))
+}
+
+fn stream_targets(stream: &serde_json::Value) -> Vec<serde_json::Value> {
+    stream["multistream"]["targets"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn set_stream_targets(
+    stream_id: &str,
+    targets: Vec<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    crate::live::update_stream(
+        stream_id,
+        serde_json::json!({ "multistream": { "targets": targets } }),
+    )
+}
+
+// Targets of a stream with a target restreamed with one of its profiles, or the source.
+// Attaching a target again replaces its profile.
+fn with_target(
+    stream: &serde_json::Value,
+    target_id: &str,
+    profile: &str,
+    video_only: bool,
+) -> Result<Vec<serde_json::Value>, String> {
+    let known_profile = profile == SOURCE_PROFILE
+        || stream["profiles"]
+            .as_array()
+            .map_or(false, |p| p.iter().any(|p| p["name"] == profile));
+    if !known_profile {
+        return Err(format!(
+            "The stream has no profile {}, use one of its profiles or {}",
+            profile, SOURCE_PROFILE
+        ));
+    }
+
+    let mut targets = stream_targets(stream);
+    targets.retain(|t| t["id"] != target_id);
+    targets.push(serde_json::json!({
+        "id": target_id,
+        "profile": profile,
+        "videoOnly": video_only,
+    }));
+    Ok(targets)
+}
+
+// Targets of a stream without a target, the others stay attached
+fn without_target(
+    stream: &serde_json::Value,
+    target_id: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+    let mut targets = stream_targets(stream);
+    let count = targets.len();
+    targets.retain(|t| t["id"] != target_id);
+    if targets.len() == count {
+        return Err(format!("Target {} is not attached to the stream", target_id));
+    }
+    Ok(targets)
+}
+
+pub fn attach(
+    stream_id: &str,
+    target_id: &str,
+    profile: &str,
+    video_only: bool,
+) -> Result<serde_json::Value, String> {
+    let stream = crate::api::get(&format!("/api/stream/{}", stream_id))?;
+    set_stream_targets(
+        stream_id,
+        with_target(&stream, target_id, profile, video_only)?,
+    )
+}
+
+pub fn detach(stream_id: &str, target_id: &str) -> Result<serde_json::Value, String> {
+    let stream = crate::api::get(&format!("/api/stream/{}", stream_id))?;
+    set_stream_targets(stream_id, without_target(&stream, target_id)?)
+}
+
+fn target_label(target: &serde_json::Value) -> String {
+    format!(
+        "{} - {} - {}{}",
+        target["id"].as_str().unwrap_or(""),
+        target["name"].as_str().unwrap_or(""),
+        target["url"].as_str().unwrap_or("(url hidden)"),
+        if target["disabled"].as_bool() == Some(true) {
+            " - disabled"
+        } else {
+            ""
+        }
+    )
+}
+
+fn select_target(prompt: &str) -> Option<serde_json::Value> {
+    let targets = match list_targets() {
+        Ok(t) => t.as_array().cloned().unwrap_or_default(),
+        Err(e) => {
+            error!("{}", e);
+            return None;
+        }
+    };
+    if targets.is_empty() {
+        warn!("No multistream targets");
+        return None;
+    }
+    let items = targets.iter().map(target_label).collect::<Vec<String>>();
+    dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(&items)
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap()
+        .map(|i| targets[i].clone())
+}
+
+fn prompt_new_target() -> Option<serde_json::Value> {
+    let name = dialoguer::Input::<String>::new()
+        .with_prompt("Target name")
+        .interact()
+        .unwrap();
+    let url = dialoguer::Input::<String>::new()
+        .with_prompt("Ingest url with the stream key, e.g. rtmp://a.rtmp.youtube.com/live2/<key>")
+        .validate_with(|u: &String| validate_url(u))
+        .interact()
+        .unwrap();
+    match create_target(&name, &url) {
+        Ok(t) => {
+            info!("Target {} created", name);
+            Some(t)
+        }
+        Err(e) => {
+            error!("{}", e);
+            None
+        }
+    }
+}
+
+// Targets saved on the account, shared by every stream
+fn manage_targets() {
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&[
+            "List Targets",
+            "Create Target",
+            "Update Target",
+            "Delete Target",
+            "< Back",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(0) => match list_targets() {
+            Ok(targets) => targets
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .iter()
+                .for_each(|t| println!("{}", target_label(t))),
+            Err(e) => error!("{}", e),
+        },
+        Some(1) => {
+            prompt_new_target();
+        }
+        Some(2) => {
+            if let Some(target) = select_target("Target to update") {
+                let name = dialoguer::Input::<String>::new()
+                    .with_prompt("Name")
+                    .default(target["name"].as_str().unwrap_or("").to_string())
+                    .interact()
+                    .unwrap();
+                let url = dialoguer::Input::<String>::new()
+                    .with_prompt("Ingest url (empty to keep the current one)")
+                    .allow_empty(true)
+                    .validate_with(|u: &String| if u.is_empty() { Ok(()) } else { validate_url(u) })
+                    .interact()
+                    .unwrap();
+                let disabled = dialoguer::Confirm::new()
+                    .with_prompt("Disable the target?")
+                    .default(target["disabled"].as_bool().unwrap_or(false))
+                    .interact()
+                    .unwrap();
+                let mut fields = serde_json::json!({ "name": name, "disabled": disabled });
+                if !url.is_empty() {
+                    fields["url"] = serde_json::json!(url);
+                }
+                match update_target(target["id"].as_str().unwrap_or(""), fields) {
+                    Ok(t) => println!("{}", target_label(&t)),
+                    Err(e) => error!("{}", e),
+                }
+            }
+        }
+        Some(3) => {
+            if let Some(target) = select_target("Target to delete") {
+                let confirm = dialoguer::Confirm::new()
+                    .with_prompt(format!(
+                        "Delete {}? Streams restreaming to it will stop",
+                        target["name"]
+                    ))
+                    .default(false)
+                    .interact()
+                    .unwrap();
+                if confirm {
+                    match delete_target(target["id"].as_str().unwrap_or("")) {
+                        Ok(_) => info!("Target deleted"),
+                        Err(e) => error!("{}", e),
+                    }
+                }
+            }
+        }
+        _ => return,
+    }
+    manage_targets();
+}
+
+fn print_stream_targets(stream: &serde_json::Value) {
+    let targets = stream_targets(stream);
+    if targets.is_empty() {
+        println!("No multistream targets attached");
+        return;
+    }
+    // Attached targets only carry the id, names come from the target list
+    let known = list_targets()
+        .ok()
+        .and_then(|t| t.as_array().cloned())
+        .unwrap_or_default();
+    for t in &targets {
+        let name = known
+            .iter()
+            .find(|k| k["id"] == t["id"])
+            .and_then(|k| k["name"].as_str())
+            .unwrap_or("unknown target");
+        println!(
+            "  {} ({}) - profile {}{}",
+            name.bold(),
+            t["id"].as_str().unwrap_or(""),
+            t["profile"].as_str().unwrap_or(""),
+            if t["videoOnly"].as_bool() == Some(true) {
+                ", video only"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+// Multistream menu of a stream, returns the stream refreshed when it changed
+pub fn multistream(stream: &serde_json::Value) -> Option<serde_json::Value> {
+    let stream_id = stream["id"].as_str().unwrap_or("").to_string();
+    print_stream_targets(stream);
+
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&[
+            "Attach Target",
+            "Detach Target",
+            "Manage Targets",
+            "< Back",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    let result = match selection {
+        Some(0) => {
+            let choice =
+                dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                    .items(&["Existing target", "New target"])
+                    .default(0)
+                    .interact_on_opt(&crate::Term::stderr())
+                    .unwrap();
+            let target = match choice {
+                Some(0) => select_target("Target to attach"),
+                Some(_) => prompt_new_target(),
+                None => None,
+            };
+            let target = match target {
+                Some(t) => t,
+                None => return multistream(stream),
+            };
+
+            let mut profiles = vec![String::from(SOURCE_PROFILE)];
+            profiles.extend(
+                stream["profiles"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|p| p["name"].as_str().map(String::from)),
+            );
+            let profile = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt("Rendition to restream")
+                .items(&profiles)
+                .default(0)
+                .interact_on_opt(&crate::Term::stderr())
+                .unwrap()
+                .unwrap_or(0);
+            let video_only = dialoguer::Confirm::new()
+                .with_prompt("Video only (drop the audio)?")
+                .default(false)
+                .interact()
+                .unwrap();
+            attach(
+                &stream_id,
+                target["id"].as_str().unwrap_or(""),
+                &profiles[profile],
+                video_only,
+            )
+        }
+        Some(1) => {
+            let targets = stream_targets(stream);
+            if targets.is_empty() {
+                return multistream(stream);
+            }
+            let items = targets
+                .iter()
+                .map(|t| {
+                    format!(
+                        "{} - {}",
+                        t["id"].as_str().unwrap_or(""),
+                        t["profile"].as_str().unwrap_or("")
+                    )
+                })
+                .collect::<Vec<String>>();
+            match dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                .with_prompt("Target to detach")
+                .items(&items)
+                .default(0)
+                .interact_on_opt(&crate::Term::stderr())
+                .unwrap()
+            {
+                Some(i) => detach(&stream_id, targets[i]["id"].as_str().unwrap_or("")),
+                None => return multistream(stream),
+            }
+        }
+        Some(2) => {
+            manage_targets();
+            return multistream(stream);
+        }
+        _ => return None,
+    };
+
+    match result {
+        Ok(updated) => {
+            info!("Multistream targets updated");
+            multistream(&updated).or(Some(updated))
+        }
+        Err(e) => {
+            error!("{}", e);
+            multistream(stream)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    // In-memory Studio api: GET, POST to a collection, PATCH and DELETE of json objects by path
+    static DB: std::sync::Mutex<Vec<(String, serde_json::Value)>> = std::sync::Mutex::new(vec![]);
+    static SERVER: std::sync::Once = std::sync::Once::new();
+
+    fn handle(
+        method: &str,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> (u16, serde_json::Value) {
+        let mut db = DB.lock().unwrap();
+        let not_found = (404, serde_json::json!({ "errors": ["not found"] }));
+        let index = db.iter().position(|(p, _)| p == path);
+        match (method, index) {
+            ("GET", Some(i)) => (200, db[i].1.clone()),
+            ("GET", None) => (
+                200,
+                db.iter()
+                    .filter(|(p, _)| {
+                        p.rsplit_once('/')
+                            .map_or(false, |(parent, _)| parent == path)
+                    })
+                    .map(|(_, v)| v.clone())
+                    .collect(),
+            ),
+            ("POST", _) => {
+                let mut created = body.unwrap();
+                let id = format!("t{}", db.len());
+                created["id"] = serde_json::json!(id);
+                db.push((format!("{}/{}", path, id), created.clone()));
+                (201, created)
+            }
+            ("PATCH", Some(i)) => {
+                for (k, v) in body.unwrap().as_object().unwrap() {
+                    db[i].1[k] = v.clone();
+                }
+                (204, serde_json::Value::Null)
+            }
+            ("DELETE", Some(i)) => {
+                db.remove(i);
+                (204, serde_json::Value::Null)
+            }
+            _ => not_found,
+        }
+    }
+
+    fn serve(stream: &mut std::net::TcpStream) -> Option<()> {
+        let mut reader = BufReader::new(stream.try_clone().ok()?);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let mut parts = line.split(' ');
+        let (method, path) = (parts.next()?.to_string(), parts.next()?.to_string());
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).ok()?;
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().ok()?;
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).ok()?;
+        let (status, value) = handle(&method, &path, serde_json::from_slice(&body).ok());
+        let text = if value.is_null() {
+            String::new()
+        } else {
+            value.to_string()
+        };
+        stream
+            .write_all(
+                format!(
+                    "HTTP/1.1 {} OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    text.len(),
+                    text
+                )
+                .as_bytes(),
+            )
+            .ok()
+    }
+
+    // Point the api at the stand-in, shared by every test since the host is read from the env
+    fn api() {
+        SERVER.call_once(|| {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            std::env::set_var(
+                "LIVEPEER_STUDIO_API_HOST",
+                format!("http://{}", listener.local_addr().unwrap()),
+            );
+            crate::auth::set_current_api_key("test");
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    serve(&mut stream.unwrap());
+                }
+            });
+        });
+    }
+
+    fn stream(id: &str, targets: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "profiles": [{ "name": "720p0" }, { "name": "360p0" }],
+            "multistream": { "targets": targets },
+        })
+    }
+
+    #[test]
+    fn attaches_with_profile() {
+        let targets =
+            with_target(&stream("s", serde_json::json!([])), "t1", "360p0", true).unwrap();
+        assert_eq!(
+            targets,
+            vec![serde_json::json!({ "id": "t1", "profile": "360p0", "videoOnly": true })]
+        );
+
+        let source = with_target(
+            &stream("s", serde_json::json!([])),
+            "t1",
+            SOURCE_PROFILE,
+            false,
+        );
+        assert_eq!(source.unwrap()[0]["profile"], SOURCE_PROFILE);
+
+        assert!(with_target(&stream("s", serde_json::json!([])), "t1", "1080p0", false).is_err());
+    }
+
+    #[test]
+    fn attaching_again_replaces_the_profile() {
+        let attached = stream(
+            "s",
+            serde_json::json!([
+                { "id": "t1", "profile": "720p0", "videoOnly": false },
+                { "id": "t2", "profile": "source", "videoOnly": false },
+            ]),
+        );
+        let targets = with_target(&attached, "t1", "360p0", false).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert_eq!(targets[0]["id"], "t2");
+        assert_eq!(targets[1]["profile"], "360p0");
+    }
+
+    #[test]
+    fn detaches_one_target() {
+        let attached = stream(
+            "s",
+            serde_json::json!([
+                { "id": "t1", "profile": "720p0", "videoOnly": false },
+                { "id": "t2", "profile": "source", "videoOnly": true },
+            ]),
+        );
+        let targets = without_target(&attached, "t1").unwrap();
+        assert_eq!(
+            targets,
+            vec![serde_json::json!({ "id": "t2", "profile": "source", "videoOnly": true })]
+        );
+        assert!(without_target(&attached, "t3").is_err());
+    }
+
+    #[test]
+    fn target_crud() {
+        api();
+        assert!(create_target("bad", "http://example.com/live").is_err());
+
+        let created = create_target("youtube", "rtmp://a.rtmp.youtube.com/live2/key").unwrap();
+        let id = created["id"].as_str().unwrap().to_string();
+        let listed = list_targets().unwrap();
+        assert!(listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["id"] == id.as_str()));
+
+        let updated =
+            update_target(&id, serde_json::json!({ "name": "yt", "disabled": true })).unwrap();
+        assert_eq!(updated["name"], "yt");
+        assert_eq!(updated["disabled"], true);
+        assert!(update_target(&id, serde_json::json!({ "url": "ftp://nope" })).is_err());
+
+        delete_target(&id).unwrap();
+        assert!(delete_target(&id).is_err());
+    }
+
+    #[test]
+    fn attach_and_detach_stream_targets() {
+        api();
+        DB.lock().unwrap().push((
+            String::from("/api/stream/s1"),
+            stream("s1", serde_json::json!([])),
+        ));
+
+        attach("s1", "t1", "720p0", false).unwrap();
+        let updated = attach("s1", "t2", SOURCE_PROFILE, true).unwrap();
+        assert_eq!(stream_targets(&updated).len(), 2);
+
+        let updated = detach("s1", "t1").unwrap();
+        assert_eq!(
+            stream_targets(&updated),
+            vec![serde_json::json!({ "id": "t2", "profile": "source", "videoOnly": true })]
+        );
+        assert!(attach("s1", "t3", "1080p0", false).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Json,
+    Ndjson,
+    Yaml,
+    Csv,
+    Table,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resource {
+    Asset,
+    Stream,
+    Task,
+    User,
+    Playback,
+    Upload,
+    Queue,
+    Import,
+    Preset,
+    StreamKey,
+    Target,
+    StreamTarget,
+    Session,
+    Push,
+    Raw,
+}
+
+impl Resource {
+    // Columns shown in csv and table output, as json paths into each entry
+    fn columns(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Resource::Asset => Some(&["id", "name", "status.phase", "createdAt", "playbackId"]),
+            Resource::Stream => Some(&["id", "name", "playbackId", "isActive"]),
+            Resource::Task => Some(&["id", "type", "status.phase", "status.progress", "outputAssetId", "createdAt"]),
+            Resource::User => Some(&["id", "email", "firstName", "lastName", "admin"]),
+            Resource::Playback => Some(&["hrn", "type", "url", "width", "height", "bitrate"]),
+            Resource::Upload => Some(&["file", "status", "assetId", "taskId", "playbackId"]),
+            Resource::Queue => Some(&["id", "status", "path", "assetId", "taskId", "error", "createdAt"]),
+            Resource::Import => Some(&["url", "name", "status", "assetId", "taskId", "playbackId", "error"]),
+            Resource::Preset => Some(&["name", "description", "builtin", "profiles"]),
+            Resource::StreamKey => Some(&["id", "name", "status", "streamKey", "ingest.rtmp", "ingest.srt", "error"]),
+            Resource::Target => Some(&["id", "name", "url", "disabled", "createdAt"]),
+            Resource::StreamTarget => Some(&["id", "profile", "videoOnly"]),
+            Resource::Session => Some(&["id", "startedAt", "endedAt", "duration", "recording", "playbackId"]),
+            Resource::Push => Some(&["id", "streamName", "region", "source", "running", "pid", "startedAt"]),
+            Resource::Raw => None,
+        }
+    }
+}
+
+pub fn truncate_and_pad(s: &str, max_width: usize, min_width: usize) -> String {
+    let truncated = if s.len() > max_width {
+        s.chars().take(max_width).collect::<String>()
+    } else {
+        s.to_string()
+    };
+    format!("{:<width$}   ", truncated, width = min_width)
+}
+
+pub fn format_timestamp(value: &serde_json::Value) -> String {
+    // API timestamps are in milliseconds, string timestamps in seconds
+    let timestamp = match value {
+        serde_json::Value::Number(n) => n.as_i64().map(|ms| ms / 1000),
+        serde_json::Value::String(s) => s.parse::<i64>().ok(),
+        _ => None,
+    };
+    match timestamp {
+        Some(t) => {
+            let naive_datetime = NaiveDateTime::from_timestamp(t, 0);
+            let datetime: DateTime<Utc> = DateTime::from_utc(naive_datetime, Utc);
+            datetime.to_rfc3339()
+        }
+        None => String::from(""),
+    }
+}
+
+pub fn render(value: &serde_json::Value, resource: Resource, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(value).unwrap(),
+        OutputFormat::Ndjson => rows(value, resource)
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap())
+            .collect::<Vec<String>>()
+            .join("\n"),
+        OutputFormat::Yaml => serde_yaml::to_string(value).unwrap().trim_end().to_string(),
+        OutputFormat::Csv => {
+            let rows = rows(value, resource);
+            let columns = columns(&rows, resource);
+            let mut writer = csv::Writer::from_writer(vec![]);
+            writer.write_record(&columns).unwrap();
+            for row in &rows {
+                writer
+                    .write_record(columns.iter().map(|c| cell(row, c)))
+                    .unwrap();
+            }
+            String::from_utf8(writer.into_inner().unwrap())
+                .unwrap()
+                .trim_end()
+                .to_string()
+        }
+        OutputFormat::Table => {
+            let rows = rows(value, resource);
+            let columns = columns(&rows, resource);
+            let cells = rows
+                .iter()
+                .map(|row| columns.iter().map(|c| cell(row, c)).collect::<Vec<String>>())
+                .collect::<Vec<Vec<String>>>();
+
+            // Size every column to its widest cell, capped so urls don't wrap the terminal
+            let widths = columns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| {
+                    cells
+                        .iter()
+                        .map(|r| r[i].chars().count())
+                        .chain(std::iter::once(c.len()))
+                        .max()
+                        .unwrap()
+                        .min(60)
+                })
+                .collect::<Vec<usize>>();
+
+            let mut table = vec![columns
+                .iter()
+                .zip(&widths)
+                .map(|(c, w)| truncate_and_pad(&c.to_uppercase(), *w, *w))
+                .collect::<String>()];
+            for row in cells {
+                table.push(
+                    row.iter()
+                        .zip(&widths)
+                        .map(|(c, w)| truncate_and_pad(c, *w, *w))
+                        .collect::<String>(),
+                );
+            }
+            table
+                .iter()
+                .map(|l| l.trim_end().to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+        }
+    }
+}
+
+fn rows(value: &serde_json::Value, resource: Resource) -> Vec<serde_json::Value> {
+    if resource == Resource::Playback {
+        if let Some(sources) = value["meta"]["source"].as_array() {
+            return sources.clone();
+        }
+    }
+    match value {
+        serde_json::Value::Array(list) => list.clone(),
+        serde_json::Value::Null => vec![],
+        v => vec![v.clone()],
+    }
+}
+
+fn columns(rows: &[serde_json::Value], resource: Resource) -> Vec<String> {
+    match resource.columns() {
+        Some(c) => c.iter().map(|c| c.to_string()).collect(),
+        None => match rows.first() {
+            Some(serde_json::Value::Object(o)) => o.keys().cloned().collect(),
+            _ => vec![String::from("value")],
+        },
+    }
+}
+
+fn cell(row: &serde_json::Value, column: &str) -> String {
+    if !row.is_object() {
+        return scalar(row);
+    }
+    let value = column.split('.').fold(row, |v, key| &v[key]);
+    if column == "createdAt" {
+        return format_timestamp(value);
+    }
+    scalar(value)
+}
+
+fn scalar(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::Null => String::from(""),
+        serde_json::Value::String(s) => s.clone(),
+        v @ serde_json::Value::Array(_) | v @ serde_json::Value::Object(_) => {
+            serde_json::to_string(v).unwrap()
+        }
+        v => v.to_string(),
+    }
+}
@@ -0,0 +1,328 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+use crate::live::StreamProfile;
+
+// Presets saved by the user live in $HOME/.studio/presets/profiles.json, built-in ones are not stored
+const PRESETS_DIR: &str = "presets";
+const PRESETS_FILE: &str = "profiles.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub profiles: Vec<StreamProfile>,
+    // Not exported, built-in presets are the same on every machine
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+fn builtin(name: &str, description: &str, profiles: Vec<StreamProfile>) -> Preset {
+    Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        profiles,
+        builtin: true,
+    }
+}
+
+pub fn builtins() -> Vec<Preset> {
+    vec![
+        builtin(
+            "default",
+            "Single 240p rendition",
+            crate::live::default_profiles(),
+        ),
+        builtin(
+            "mobile",
+            "360p and 240p for mobile viewers",
+            vec![
+                StreamProfile::new(360, 640, 800000, 0, None),
+                StreamProfile::new(240, 426, 250000, 0, None),
+            ],
+        ),
+        builtin(
+            "sd",
+            "720p, 480p and 360p",
+            vec![
+                StreamProfile::new(720, 1280, 3000000, 0, None),
+                StreamProfile::new(480, 854, 1600000, 0, None),
+                StreamProfile::new(360, 640, 800000, 0, None),
+            ],
+        ),
+        builtin(
+            "hd",
+            "Full 1080p ABR ladder",
+            vec![
+                StreamProfile::new(1080, 1920, 6000000, 0, None),
+                StreamProfile::new(720, 1280, 3000000, 0, None),
+                StreamProfile::new(480, 854, 1600000, 0, None),
+                StreamProfile::new(360, 640, 800000, 0, None),
+            ],
+        ),
+    ]
+}
+
+fn load() -> Vec<Preset> {
+    crate::auth::get_string_from_disk(&String::from(PRESETS_DIR), &String::from(PRESETS_FILE))
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(presets: &Vec<Preset>) {
+    crate::auth::save_string_to_disk(
+        &String::from(PRESETS_DIR),
+        &String::from(PRESETS_FILE),
+        &serde_json::to_string_pretty(presets).unwrap(),
+    );
+}
+
+// Built-in presets first, then the saved ones
+pub fn all() -> Vec<Preset> {
+    let mut presets = builtins();
+    presets.extend(load());
+    presets
+}
+
+pub fn find(name: &String) -> Result<Preset, String> {
+    all().into_iter().find(|p| &p.name == name).ok_or(format!(
+        "No preset {}, available presets: {}",
+        name,
+        all()
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+fn validate(preset: &Preset) -> Result<(), String> {
+    if preset.name.trim().is_empty() {
+        return Err(String::from("Preset name can't be empty"));
+    }
+    if builtins().iter().any(|p| p.name == preset.name) {
+        return Err(format!("{} is a built-in preset", preset.name));
+    }
+    if preset.profiles.is_empty() {
+        return Err(format!("Preset {} has no profiles", preset.name));
+    }
+    Ok(())
+}
+
+// Save a preset, replacing a saved preset with the same name
+pub fn save_preset(preset: Preset) -> Result<(), String> {
+    validate(&preset)?;
+    let mut presets = load();
+    presets.retain(|p| p.name != preset.name);
+    presets.push(preset);
+    save(&presets);
+    Ok(())
+}
+
+pub fn remove(name: &String) -> Result<(), String> {
+    if builtins().iter().any(|p| &p.name == name) {
+        return Err(format!("{} is a built-in preset and can't be removed", name));
+    }
+    let mut presets = load();
+    let count = presets.len();
+    presets.retain(|p| &p.name != name);
+    if presets.len() == count {
+        return Err(format!("No saved preset {}", name));
+    }
+    save(&presets);
+    Ok(())
+}
+
+// Write the given presets, or every saved one when none are given, as a json array
+pub fn export(path: &String, names: &Vec<String>) -> Result<usize, String> {
+    let presets = if names.is_empty() {
+        load()
+    } else {
+        names.iter().map(find).collect::<Result<Vec<_>, _>>()?
+    };
+    std::fs::write(path, serde_json::to_string_pretty(&presets).unwrap())
+        .map_err(|e| format!("Error writing {}: {}", path, e))?;
+    Ok(presets.len())
+}
+
+// Save every preset of a json array, presets already saved with the same name are replaced
+pub fn import(path: &String) -> Result<usize, String> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| format!("Error reading {}: {}", path, e))?;
+    let mut imported: Vec<Preset> =
+        serde_json::from_str(&contents).map_err(|e| format!("Invalid presets {}: {}", path, e))?;
+    // Exported built-in presets are skipped, they already exist
+    imported.retain(|i| {
+        let builtin = builtins().iter().any(|p| p.name == i.name);
+        if builtin {
+            warn!("Skipping built-in preset {}", i.name);
+        }
+        !builtin
+    });
+    for preset in &imported {
+        validate(preset)?;
+    }
+
+    let mut presets = load();
+    presets.retain(|p| !imported.iter().any(|i| i.name == p.name));
+    presets.extend(imported.iter().cloned());
+    save(&presets);
+    Ok(imported.len())
+}
+
+pub fn to_value(presets: &Vec<Preset>) -> serde_json::Value {
+    presets
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "name": p.name,
+                "description": p.description,
+                "builtin": p.builtin,
+                "profiles": p.profiles.iter().map(|r| r.name.clone()).collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+fn label(preset: &Preset) -> String {
+    format!(
+        "{} - {} ({}){}",
+        preset.name,
+        preset.description,
+        preset
+            .profiles
+            .iter()
+            .map(|p| p.name.clone())
+            .collect::<Vec<_>>()
+            .join(", "),
+        if preset.builtin { "" } else { " *" }
+    )
+}
+
+// Pick a preset or build a custom ladder, which can be saved as a new preset
+pub fn select_profiles() -> Vec<StreamProfile> {
+    let presets = all();
+    let mut items = presets.iter().map(label).collect::<Vec<String>>();
+    items.push(String::from("Custom"));
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Transcoding profiles")
+        .items(&items)
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(index) if index < presets.len() => presets[index].profiles.clone(),
+        Some(_) => {
+            let profiles = crate::live::prompt_profiles(vec![]);
+            let save_as = dialoguer::Input::<String>::new()
+                .with_prompt("Save as a preset named (empty to not save)")
+                .allow_empty(true)
+                .interact()
+                .unwrap();
+            if !save_as.is_empty() {
+                let preset = Preset {
+                    name: save_as,
+                    description: String::new(),
+                    profiles: profiles.clone(),
+                    builtin: false,
+                };
+                if let Err(e) = save_preset(preset) {
+                    error!("{}", e);
+                }
+            }
+            profiles
+        }
+        None => crate::live::default_profiles(),
+    }
+}
+
+pub fn presets() {
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&[
+            "List Presets",
+            "Create Preset",
+            "Delete Preset",
+            "Export Presets to JSON",
+            "Import Presets from JSON",
+            "< Back",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(0) => {
+            for preset in all() {
+                println!("{}", label(&preset).bold());
+                crate::live::print_profiles(&preset.profiles);
+            }
+        }
+        Some(1) => {
+            let name = dialoguer::Input::<String>::new()
+                .with_prompt("Preset name")
+                .interact()
+                .unwrap();
+            let description = dialoguer::Input::<String>::new()
+                .with_prompt("Description")
+                .allow_empty(true)
+                .interact()
+                .unwrap();
+            let preset = Preset {
+                name,
+                description,
+                profiles: crate::live::prompt_profiles(vec![]),
+                builtin: false,
+            };
+            match save_preset(preset) {
+                Ok(_) => info!("Preset saved"),
+                Err(e) => error!("{}", e),
+            }
+        }
+        Some(2) => {
+            let saved = load();
+            if saved.is_empty() {
+                warn!("No saved presets, built-in presets can't be deleted");
+                return presets();
+            }
+            let items = saved.iter().map(label).collect::<Vec<String>>();
+            if let Some(index) =
+                dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+                    .items(&items)
+                    .default(0)
+                    .interact_on_opt(&crate::Term::stderr())
+                    .unwrap()
+            {
+                match remove(&saved[index].name) {
+                    Ok(_) => info!("Preset {} deleted", saved[index].name),
+                    Err(e) => error!("{}", e),
+                }
+            }
+        }
+        Some(3) => {
+            let path = dialoguer::Input::<String>::new()
+                .with_prompt("Export the saved presets to")
+                .default(String::from("presets.json"))
+                .interact()
+                .unwrap();
+            match export(&path, &vec![]) {
+                Ok(count) => info!("{} presets exported to {}", count, path),
+                Err(e) => error!("{}", e),
+            }
+        }
+        Some(4) => {
+            let path = dialoguer::Input::<String>::new()
+                .with_prompt("Import presets from")
+                .default(String::from("presets.json"))
+                .interact()
+                .unwrap();
+            match import(&path) {
+                Ok(count) => info!("{} presets imported from {}", count, path),
+                Err(e) => error!("{}", e),
+            }
+        }
+        _ => return,
+    }
+    presets();
+}
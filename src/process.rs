@@ -0,0 +1,126 @@
+// Processes started by an earlier invocation of the CLI, tracked by pid in files under ~/.studio
+
+pub fn is_running(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        return std::path::Path::new(&format!("/proc/{}", pid)).exists();
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        return std::process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+    }
+}
+
+// When a running process started, in a format only meant to be compared with another start time
+// of the same system
+pub fn start_time(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        // Field 22 of stat, in clock ticks since boot. The command name in field 2 may contain
+        // spaces, so fields are counted from the parenthesis closing it.
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let (_, fields) = stat.rsplit_once(')')?;
+        return fields.split_whitespace().nth(19).map(String::from);
+    }
+
+    #[cfg(all(not(target_os = "linux"), not(target_os = "windows")))]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "lstart=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        return Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|s| output.status.success() && !s.is_empty());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let output = std::process::Command::new("wmic")
+            .args([
+                "process",
+                "where",
+                &format!("ProcessId={}", pid),
+                "get",
+                "CreationDate",
+                "/value",
+            ])
+            .output()
+            .ok()?;
+        return String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix("CreationDate=")
+            .map(String::from);
+    }
+}
+
+// Whether `pid` is still the process that was recorded with `started`, rather than another one
+// that reused its pid. Without a recorded start time only a running process is checked.
+pub fn is_same_process(pid: u32, started: Option<&str>) -> bool {
+    match started {
+        Some(started) => start_time(pid).map_or(false, |s| s == started),
+        None => is_running(pid),
+    }
+}
+
+// Command line of a running process with its arguments separated by spaces, used to tell
+// whether a pid still belongs to the process that was recorded or has been reused
+pub fn command_line(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        return Some(String::from_utf8_lossy(&cmdline).replace('\0', " "));
+    }
+
+    #[cfg(all(not(target_os = "linux"), not(target_os = "windows")))]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "command=", "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        return Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|c| output.status.success() && !c.is_empty());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let output = std::process::Command::new("wmic")
+            .args([
+                "process",
+                "where",
+                &format!("ProcessId={}", pid),
+                "get",
+                "CommandLine",
+                "/value",
+            ])
+            .output()
+            .ok()?;
+        return String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .strip_prefix("CommandLine=")
+            .map(String::from);
+    }
+}
+
+pub fn kill(pid: u32) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    let output = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string()])
+        .output();
+
+    #[cfg(not(target_os = "windows"))]
+    let output = std::process::Command::new("kill")
+        .arg(pid.to_string())
+        .output();
+
+    match output {
+        Ok(o) if o.status.success() => Ok(()),
+        Ok(o) => Err(String::from_utf8_lossy(&o.stderr).trim().to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
@@ -0,0 +1,358 @@
+use colored::*;
+use serde::{Deserialize, Serialize};
+
+// Running pushes are tracked in $HOME/.studio/pushes/pushes.json, with the ffmpeg output of
+// each push in $HOME/.studio/pushes/<id>.log, so they can be managed from another invocation
+const PUSHES_DIR: &str = "pushes";
+const PUSHES_FILE: &str = "pushes.json";
+
+static PUSHES_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+// Pushes started by this process, waited on so finished ones don't linger as zombies
+static CHILDREN: std::sync::Mutex<Vec<std::process::Child>> = std::sync::Mutex::new(vec![]);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Push {
+    pub id: String,
+    pub pid: u32,
+    pub stream_id: String,
+    pub stream_name: String,
+    pub playback_id: String,
+    pub region: String,
+    // File pushed, or a description of the generated source
+    pub source: String,
+    pub log: String,
+    pub started_at: i64,
+    #[serde(skip_deserializing)]
+    pub running: bool,
+}
+
+fn pushes_dir() -> std::path::PathBuf {
+    let dir = dirs::home_dir().unwrap().join(".studio").join(PUSHES_DIR);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn load() -> Vec<Push> {
+    std::fs::read_to_string(pushes_dir().join(PUSHES_FILE))
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn save(pushes: &Vec<Push>) {
+    std::fs::write(
+        pushes_dir().join(PUSHES_FILE),
+        serde_json::to_string_pretty(pushes).unwrap(),
+    )
+    .unwrap();
+}
+
+fn reap() {
+    CHILDREN
+        .lock()
+        .unwrap()
+        .retain_mut(|c| c.try_wait().map_or(false, |s| s.is_none()));
+}
+
+// ffmpeg arguments reading a file in real time, from the start again when it ends if looped
+pub fn file_input(file: &String, looped: bool) -> Vec<String> {
+    let mut args = vec![String::from("-re")];
+    if looped {
+        args.extend([String::from("-stream_loop"), String::from("-1")]);
+    }
+    args.extend([String::from("-i"), file.clone()]);
+    args
+}
+
+// Generated test pattern with the local wall clock burned in and a 1kHz tone, so ingest can be
+// checked without a media file. Latency shows as the gap between the burned-in and real time.
+#[derive(Clone, Debug)]
+pub struct TestSource {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    // Seconds, 0 to push until stopped
+    pub duration: u64,
+}
+
+impl TestSource {
+    pub fn label(&self) -> String {
+        format!(
+            "test pattern {}x{}@{}{}",
+            self.width,
+            self.height,
+            self.fps,
+            if self.duration > 0 {
+                format!(" for {}s", self.duration)
+            } else {
+                String::new()
+            }
+        )
+    }
+
+    // ffmpeg arguments generating the pattern and tone in real time, needs drawtext (libfreetype)
+    pub fn input_args(&self) -> Vec<String> {
+        let video = format!(
+            "testsrc2=size={}x{}:rate={},drawtext=text='%{{localtime\\:%Y-%m-%d %T}}':fontsize={}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw={}:x=(w-text_w)/2:y=h-text_h-{}",
+            self.width,
+            self.height,
+            self.fps,
+            (self.height / 12).max(12),
+            (self.height / 90).max(4),
+            self.height / 16,
+        );
+        let mut args = vec![
+            String::from("-re"),
+            String::from("-f"),
+            String::from("lavfi"),
+            String::from("-i"),
+            video,
+            String::from("-re"),
+            String::from("-f"),
+            String::from("lavfi"),
+            String::from("-i"),
+            String::from("sine=frequency=1000:sample_rate=48000"),
+            // testsrc2 isn't 4:2:0 by default, which most players can't decode
+            String::from("-pix_fmt"),
+            String::from("yuv420p"),
+        ];
+        if self.duration > 0 {
+            args.extend([String::from("-t"), self.duration.to_string()]);
+        }
+        args
+    }
+}
+
+// Resolution as WIDTHxHEIGHT, even for 4:2:0
+pub fn parse_size(size: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("Invalid size {}, expected WIDTHxHEIGHT (e.g. 1280x720)", size);
+    let (width, height) = size.trim().split_once('x').ok_or_else(invalid)?;
+    let width = width.parse::<u32>().map_err(|_| invalid())?;
+    let height = height.parse::<u32>().map_err(|_| invalid())?;
+    if width == 0 || height == 0 || width % 2 != 0 || height % 2 != 0 {
+        return Err(format!("Invalid size {}, width and height must be even", size));
+    }
+    Ok((width, height))
+}
+
+// H.264/AAC with a keyframe every 2 seconds, capped when a bandwidth limit is set
+pub fn encoding_args() -> Vec<String> {
+    let mut args = match crate::ratelimit::limit() {
+        Some(limit) => crate::ratelimit::push_bitrate_args(limit),
+        None => ["-c:v", "libx264", "-preset", "veryfast", "-c:a", "aac", "-b:a", "128k"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+    };
+    args.extend([
+        String::from("-force_key_frames"),
+        String::from("expr:gte(t,n_forced*2)"),
+    ]);
+    args
+}
+
+// Tag set on the output of a push, so its pid can be told apart from a reused one
+fn marker(id: &str) -> String {
+    format!("comment=studio-push-{}", id)
+}
+
+// Whether the pid of a push is still its ffmpeg. Entries outlive reboots and pids get reused,
+// a pid running anything else must not be reported or killed as the push.
+fn is_running(push: &Push) -> bool {
+    crate::process::command_line(push.pid)
+        .map_or(false, |c| c.contains("ffmpeg") && c.contains(&marker(&push.id)))
+}
+
+// Start pushing to a stream in the background, the push keeps running after the CLI exits
+pub fn start(
+    stream: &serde_json::Value,
+    region: &String,
+    source: &String,
+    input_args: Vec<String>,
+) -> Result<Push, String> {
+    let ffmpeg = crate::live::get_ffmpeg_path()?;
+    let stream_key = stream["streamKey"].as_str().unwrap_or("");
+    let url = format!("{}/{}", crate::api::regional_rtmp(region), stream_key);
+
+    let id = nanoid::nanoid!(8);
+    let log = pushes_dir().join(format!("{}.log", id));
+    let log_file = std::fs::File::create(&log).map_err(|e| e.to_string())?;
+
+    let mut command = std::process::Command::new(ffmpeg);
+    // Own process group, so Ctrl-C in the CLI doesn't stop the push
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let child = command
+        .args(["-nostdin", "-hide_banner"])
+        .args(&input_args)
+        .args(encoding_args())
+        .args(["-metadata", &marker(&id)])
+        .args(["-f", "flv", &url])
+        .stdin(std::process::Stdio::null())
+        .stdout(log_file.try_clone().map_err(|e| e.to_string())?)
+        .stderr(log_file)
+        .spawn()
+        .map_err(|e| format!("Error starting ffmpeg: {}", e))?;
+
+    let push = Push {
+        id,
+        pid: child.id(),
+        stream_id: stream["id"].as_str().unwrap_or("").to_string(),
+        stream_name: stream["name"].as_str().unwrap_or("").to_string(),
+        playback_id: stream["playbackId"].as_str().unwrap_or("").to_string(),
+        region: region.clone(),
+        source: source.clone(),
+        log: log.to_str().unwrap().to_string(),
+        started_at: chrono::Utc::now().timestamp_millis(),
+        running: true,
+    };
+    CHILDREN.lock().unwrap().push(child);
+
+    let _lock = PUSHES_LOCK.lock().unwrap();
+    let mut pushes = load();
+    pushes.push(push.clone());
+    save(&pushes);
+    info!("Pushing {} to {} in {} ({})", source, push.stream_name, region, push.id);
+    Ok(push)
+}
+
+// Every tracked push, of one stream when given, with whether its ffmpeg is still running
+pub fn list(stream_id: Option<&str>) -> Vec<Push> {
+    reap();
+    load()
+        .into_iter()
+        .filter(|p| stream_id.map_or(true, |s| p.stream_id == s))
+        .map(|mut p| {
+            p.running = is_running(&p);
+            p
+        })
+        .collect()
+}
+
+fn find(id: &str) -> Result<Push, String> {
+    list(None)
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or(format!("No push {}", id))
+}
+
+// Stop a push if it's still running and forget it, along with its log
+pub fn stop(id: &str) -> Result<Push, String> {
+    let push = find(id)?;
+    if push.running {
+        crate::process::kill(push.pid).map_err(|e| format!("Error stopping push {}: {}", id, e))?;
+    }
+    let _ = std::fs::remove_file(&push.log);
+
+    let _lock = PUSHES_LOCK.lock().unwrap();
+    let mut pushes = load();
+    pushes.retain(|p| p.id != id);
+    save(&pushes);
+    reap();
+    info!("Stopped push {} to {} in {}", id, push.stream_name, push.region);
+    Ok(push)
+}
+
+// Stop every push, or every push of a stream, carrying on past failures
+pub fn stop_all(stream_id: Option<&str>) -> Vec<Result<Push, String>> {
+    list(stream_id).iter().map(|p| stop(&p.id)).collect()
+}
+
+// Last lines of the ffmpeg output of a push, progress updates (\r) are split into lines
+pub fn tail(id: &str, lines: usize) -> Result<Vec<String>, String> {
+    let push = find(id)?;
+    let contents = std::fs::read_to_string(&push.log)
+        .map_err(|e| format!("Error reading {}: {}", push.log, e))?;
+    let all = contents
+        .split(|c| c == '\n' || c == '\r')
+        .filter(|l| !l.trim().is_empty())
+        .map(String::from)
+        .collect::<Vec<String>>();
+    Ok(all[all.len().saturating_sub(lines)..].to_vec())
+}
+
+// Print the output of a push as it's written, until the push exits
+pub fn follow(id: &str) -> Result<(), String> {
+    let push = find(id)?;
+    let mut printed = 0;
+    loop {
+        let contents = std::fs::read(&push.log).map_err(|e| e.to_string())?;
+        if contents.len() > printed {
+            eprint!("{}", String::from_utf8_lossy(&contents[printed..]));
+            printed = contents.len();
+        }
+        reap();
+        if !is_running(&push) {
+            eprintln!();
+            info!("Push {} exited", id);
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+pub fn to_value(pushes: &Vec<Push>) -> serde_json::Value {
+    serde_json::to_value(pushes).unwrap()
+}
+
+fn label(push: &Push) -> String {
+    format!(
+        "{} - {} - {} - {} - {}",
+        push.id,
+        push.stream_name,
+        push.region,
+        push.source,
+        if push.running {
+            "running".green()
+        } else {
+            "exited".red()
+        }
+    )
+}
+
+// Running pushes menu, of one stream when given
+pub fn pushes(stream_id: Option<&str>) {
+    let tracked = list(stream_id);
+    if tracked.is_empty() {
+        info!("No pushes");
+        return;
+    }
+
+    let mut items = vec![String::from("< Back"), String::from("Stop all")];
+    items.extend(tracked.iter().map(label));
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Pushes")
+        .items(&items)
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    let push = match selection {
+        Some(1) => {
+            for result in stop_all(stream_id) {
+                if let Err(e) = result {
+                    error!("{}", e);
+                }
+            }
+            return;
+        }
+        Some(index) if index > 1 => &tracked[index - 2],
+        _ => return,
+    };
+
+    let action = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&["Show output", "Stop", "< Back"])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+    let result = match action {
+        Some(0) => tail(&push.id, 20).map(|lines| lines.iter().for_each(|l| println!("{}", l))),
+        Some(1) => stop(&push.id).map(|_| ()),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+    }
+    pushes(stream_id);
+}
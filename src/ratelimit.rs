@@ -0,0 +1,72 @@
+// Bandwidth limit shared by every upload and push of the process, in bytes per second
+static LIMIT: std::sync::Mutex<Option<u64>> = std::sync::Mutex::new(None);
+// Time at which the bytes already sent are paid for at the limited rate
+static NEXT_FREE: std::sync::Mutex<Option<std::time::Instant>> = std::sync::Mutex::new(None);
+
+// Audio bitrate kept when capping RTMP pushes
+const PUSH_AUDIO_BITRATE: u64 = 128_000;
+
+// Parse a rate like curl's --limit-rate: bytes per second with an optional K, M or G suffix
+pub fn parse_rate(rate: &str) -> Result<u64, String> {
+    let rate = rate.trim();
+    let (number, multiplier) = match rate.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&rate[..rate.len() - 1], 1024),
+        Some('M') => (&rate[..rate.len() - 1], 1024 * 1024),
+        Some('G') => (&rate[..rate.len() - 1], 1024 * 1024 * 1024),
+        _ => (rate, 1),
+    };
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid rate {}, use e.g. 500K or 20M", rate))?;
+    if number <= 0.0 {
+        return Err(format!("Invalid rate {}, it must be positive", rate));
+    }
+    Ok((number * multiplier as f64) as u64)
+}
+
+pub fn set_limit(limit: Option<u64>) {
+    *LIMIT.lock().unwrap() = limit;
+}
+
+pub fn limit() -> Option<u64> {
+    *LIMIT.lock().unwrap()
+}
+
+// Largest read worth doing at once, about 1/20s of transfer so the rate stays smooth
+pub fn max_read(limit: u64) -> usize {
+    (limit / 20).max(16 * 1024) as usize
+}
+
+// Account for `bytes` being sent and return how long to wait before sending more
+pub fn reserve(bytes: usize, limit: u64) -> std::time::Duration {
+    let mut next_free = NEXT_FREE.lock().unwrap();
+    let now = std::time::Instant::now();
+    // Wakeups come late, catch up on up to a second of unused bandwidth to keep the average
+    let start = next_free
+        .filter(|n| now.saturating_duration_since(*n) < std::time::Duration::from_secs(1))
+        .unwrap_or(now);
+    let end = start + std::time::Duration::from_secs_f64(bytes as f64 / limit as f64);
+    *next_free = Some(end);
+    end.saturating_duration_since(now)
+}
+
+// ffmpeg arguments capping the video bitrate so a push stays under the limit
+pub fn push_bitrate_args(limit: u64) -> Vec<String> {
+    let video_bitrate = (limit * 8).saturating_sub(PUSH_AUDIO_BITRATE).max(100_000);
+    vec![
+        String::from("-c:v"),
+        String::from("libx264"),
+        String::from("-preset"),
+        String::from("veryfast"),
+        String::from("-b:v"),
+        video_bitrate.to_string(),
+        String::from("-maxrate"),
+        video_bitrate.to_string(),
+        String::from("-bufsize"),
+        (video_bitrate * 2).to_string(),
+        String::from("-c:a"),
+        String::from("aac"),
+        String::from("-b:a"),
+        PUSH_AUDIO_BITRATE.to_string(),
+    ]
+}
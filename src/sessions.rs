@@ -0,0 +1,191 @@
+use colored::*;
+use livepeer_rs::playback::Playback;
+
+// Sessions of a stream, most recent first, with their recording when there is one
+pub fn list_sessions(stream_id: &str) -> Result<Vec<serde_json::Value>, String> {
+    let sessions = crate::api::get(&format!("/api/stream/{}/sessions?record=1", stream_id))
+        .map_err(|e| format!("Error getting sessions: {}", e))?;
+    let mut sessions = sessions.as_array().cloned().unwrap_or_default();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s["createdAt"].as_i64().unwrap_or(0)));
+    Ok(sessions)
+}
+
+pub fn get_session(session_id: &str) -> Result<serde_json::Value, String> {
+    crate::api::get(&format!("/api/session/{}", session_id))
+        .map_err(|e| format!("Error getting session: {}", e))
+}
+
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// The recording url to import, the mp4 when Studio generated one
+fn recording_url(session: &serde_json::Value) -> Option<String> {
+    session["mp4Url"]
+        .as_str()
+        .or(session["recordingUrl"].as_str())
+        .filter(|u| !u.is_empty())
+        .map(String::from)
+}
+
+pub fn has_recording(session: &serde_json::Value) -> bool {
+    session["recordingStatus"] == "ready" && recording_url(session).is_some()
+}
+
+pub fn session_summary(session: &serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "id": session["id"],
+        "startedAt": crate::output::format_timestamp(&session["createdAt"]),
+        "endedAt": crate::output::format_timestamp(&session["lastSeen"]),
+        "duration": format_duration(session["sourceSegmentsDuration"].as_f64().unwrap_or(0.0)),
+        "recording": session["recordingStatus"].as_str().unwrap_or("none"),
+        "playbackId": session["playbackId"],
+        "recordingUrl": recording_url(session),
+    })
+}
+
+// Import the recording of a session as an asset, returns the import (asset and task)
+pub fn save_recording(
+    session: &serde_json::Value,
+    name: String,
+    playback_policy: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let url = match recording_url(session) {
+        Some(u) if has_recording(session) => u,
+        _ => {
+            return Err(format!(
+                "Session {} has no ready recording",
+                session["id"].as_str().unwrap_or("")
+            ))
+        }
+    };
+    crate::assets::upload::retry::RetryPolicy::default().run("Importing recording", || {
+        crate::assets::upload::import_url(&url, &name, &playback_policy)
+    })
+}
+
+fn session_label(session: &serde_json::Value) -> String {
+    let summary = session_summary(session);
+    format!(
+        "{} - {} -> {} - {} - recording {}",
+        summary["id"].as_str().unwrap_or(""),
+        summary["startedAt"].as_str().unwrap_or(""),
+        summary["endedAt"].as_str().unwrap_or(""),
+        summary["duration"].as_str().unwrap_or(""),
+        if has_recording(session) {
+            "ready".green()
+        } else {
+            summary["recording"].as_str().unwrap_or("none").normal()
+        }
+    )
+}
+
+fn inspect_session(
+    client: &livepeer_rs::Livepeer,
+    stream: &serde_json::Value,
+    session: &serde_json::Value,
+) {
+    println!("{}", serde_json::to_string_pretty(session).unwrap());
+
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .items(&[
+            "Play recording",
+            "Save recording as asset",
+            "Clip recording",
+            "< Back",
+        ])
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(0) => {
+            let playback_id = match session["playbackId"].as_str() {
+                Some(p) if has_recording(session) => p,
+                _ => {
+                    warn!("This session has no recording to play");
+                    return inspect_session(client, stream, session);
+                }
+            };
+            match client
+                .playback
+                .get_playback_info(&String::from(playback_id))
+            {
+                Ok(p) => crate::playback::playback(p, client),
+                Err(e) => error!("Error getting playback info: {:?}", e),
+            }
+        }
+        Some(1) => {
+            if !has_recording(session) {
+                warn!("This session has no recording to save");
+                return inspect_session(client, stream, session);
+            }
+            let name = dialoguer::Input::<String>::new()
+                .with_prompt("Asset name")
+                .default(format!(
+                    "Recording {}",
+                    session_summary(session)["startedAt"].as_str().unwrap_or("")
+                ))
+                .interact()
+                .unwrap();
+            let playback_policy = crate::accesscontrol::generate_playback_policy(client);
+            match save_recording(session, name, playback_policy) {
+                Ok(imported) => {
+                    info!(
+                        "Asset {} created, importing the recording",
+                        imported["asset"]["id"].as_str().unwrap_or("")
+                    );
+                    crate::tasks::track_task_status(imported["task"].clone(), client);
+                }
+                Err(e) => error!("{}", e),
+            }
+            inspect_session(client, stream, session);
+        }
+        Some(2) => {
+            crate::clips::clip_recording(
+                client,
+                stream["playbackId"].as_str().unwrap_or(""),
+                session,
+            );
+            inspect_session(client, stream, session);
+        }
+        _ => {}
+    }
+}
+
+pub fn sessions(client: &livepeer_rs::Livepeer, stream: &serde_json::Value) {
+    let list = match list_sessions(stream["id"].as_str().unwrap_or("")) {
+        Ok(s) => s,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
+    if list.is_empty() {
+        warn!("No sessions found");
+        return;
+    }
+
+    let mut items = vec![String::from("< Back")];
+    items.extend(list.iter().map(session_label));
+    let selection = dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt(format!("{} sessions", list.len()))
+        .items(&items)
+        .default(0)
+        .interact_on_opt(&crate::Term::stderr())
+        .unwrap();
+
+    match selection {
+        Some(index) if index > 0 => {
+            inspect_session(client, stream, &list[index - 1]);
+            sessions(client, stream);
+        }
+        _ => {}
+    }
+}
@@ -209,6 +209,16 @@ pub fn inspect_task(task: Option<serde_json::Value>, client: &livepeer_rs::Livep
 
 use std::{cmp::min, fmt::Write};
 
+// Progress bar style shared by task tracking and uploads, `info` is shown after the bar
+pub fn progress_style(info: &str) -> indicatif::ProgressStyle {
+    indicatif::ProgressStyle::with_template(&format!(
+        "{{spinner:.green}} [{{elapsed_precise}}] [{{wide_bar:.cyan/blue}}] {}",
+        info
+    ))
+    .unwrap()
+    .progress_chars("#>-")
+}
+
 pub fn track_task_status(task: serde_json::Value, client: &livepeer_rs::Livepeer) -> bool {
     // Get task.id, then get task from livepeer client and check status.phase and status.progress
     // Spawn a indicatif progress bar and update it with the progress value
@@ -218,13 +228,7 @@ pub fn track_task_status(task: serde_json::Value, client: &livepeer_rs::Livepeer
     let mut task = client.task.get_task_by_id(String::from(task_id));
 
     let pb = indicatif::ProgressBar::new(100);
-    pb.set_style(
-        indicatif::ProgressStyle::with_template(
-            "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {msg}",
-        )
-        .unwrap()
-        .progress_chars("#>-"),
-    );
+    pb.set_style(progress_style("{msg}"));
     pb.enable_steady_tick(std::time::Duration::from_millis(120));
 
     loop {
@@ -257,6 +261,7 @@ pub fn track_task_status(task: serde_json::Value, client: &livepeer_rs::Livepeer
             if phase == "completed" || phase == "failed" {
                 pb.finish();
                 println!("Task status completion reached: {}", phase);
+                result = phase == "completed";
                 if phase == "failed" {
                     error!("Task failed");
                 }
@@ -274,3 +279,34 @@ pub fn track_task_status(task: serde_json::Value, client: &livepeer_rs::Livepeer
     }
     return result;
 }
+
+// Poll a task until it completes, reporting progress on `pb`. Meant for tracking many tasks
+// at once, so nothing is printed and failures are returned.
+pub fn wait_for_task(
+    client: &livepeer_rs::Livepeer,
+    task_id: &String,
+    pb: &indicatif::ProgressBar,
+) -> Result<serde_json::Value, String> {
+    loop {
+        let task = client
+            .task
+            .get_task_by_id(task_id.clone())
+            .map_err(|e| format!("Error getting task {}: {:?}", task_id, e))?;
+        if let Some(p) = task["status"]["progress"].as_f64() {
+            pb.set_position((p * 100.0) as u64);
+        }
+        match task["status"]["phase"].as_str() {
+            Some("completed") => {
+                pb.finish();
+                return Ok(task);
+            }
+            Some("failed") => {
+                pb.abandon();
+                return Err(task["status"]["errorMessage"]
+                    .as_str()
+                    .map_or(format!("Task {} failed", task_id), |m| m.to_string()));
+            }
+            _ => std::thread::sleep(std::time::Duration::from_secs(3)),
+        }
+    }
+}